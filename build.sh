@@ -5,9 +5,16 @@ if ! command -v wasm-pack &> /dev/null; then
     curl https://rustwasm.github.io/wasm-pack/installer/init.sh -sSf | sh
 fi
 
-# Build the WebAssembly module
+# Build the WebAssembly module. With --parallel, build the multi-threaded version: it needs
+# nightly Rust for a std with atomics, and the page must be cross-origin isolated
 cd glitch-wasm
-wasm-pack build --target web
+if [ "$1" == "--parallel" ]; then
+    rustup component add rust-src --toolchain nightly
+    RUSTFLAGS='-C target-feature=+atomics,+bulk-memory,+mutable-globals,+simd128' \
+        rustup run nightly wasm-pack build --target web -- --features parallel -Z build-std=panic_abort,std
+else
+    wasm-pack build --target web
+fi
 
 # Install npm dependencies
 cd ..
@@ -0,0 +1,99 @@
+// Scalar vs block (SIMD) kernel timings. That both versions produce identical output is
+// checked by the unit tests in src/kernels.rs, which this bench compiles in directly.
+//
+//     cargo bench --bench kernels
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+// Only the kernels are used here, not the unit tests that come with them
+#[allow(dead_code, unused_imports)]
+#[path = "../src/kernels.rs"]
+mod kernels;
+
+const PIXELS: usize = 1920 * 1080;
+const ITERATIONS: u32 = 20;
+
+fn random_bytes(rng: &mut SmallRng, len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rng.fill(&mut bytes[..]);
+    bytes
+}
+
+fn time<F: FnMut(&mut [u8])>(input: &[u8], mut f: F) -> Duration {
+    let mut data = input.to_vec();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f(black_box(&mut data));
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn compare<S, V>(name: &str, input: &[u8], mut scalar: S, mut simd: V)
+where
+    S: FnMut(&mut [u8]),
+    V: FnMut(&mut [u8]),
+{
+    let scalar_time = time(input, &mut scalar);
+    let simd_time = time(input, &mut simd);
+    println!(
+        "{name:<12} scalar {:>8.2?}  simd {:>8.2?}  speedup {:.2}x",
+        scalar_time,
+        simd_time,
+        scalar_time.as_secs_f64() / simd_time.as_secs_f64()
+    );
+}
+
+fn main() {
+    let mut rng = SmallRng::seed_from_u64(0x61177c4);
+    let image = random_bytes(&mut rng, PIXELS * 4);
+    let secondary = random_bytes(&mut rng, PIXELS * 4);
+
+    println!("{PIXELS} pixels, {ITERATIONS} iterations");
+
+    let mask = kernels::invert_mask(&[0, 2]);
+    compare("invert", &image, |d| kernels::invert_scalar(d, mask), |d| kernels::invert(d, mask));
+
+    for levels in [2, 5, 16] {
+        let table = kernels::quantize_table(levels);
+        compare(
+            &format!("quantize/{levels}"),
+            &image,
+            |d| kernels::quantize_scalar(d, levels),
+            |d| kernels::quantize(d, &table),
+        );
+    }
+
+    for max in [1, 77, 255] {
+        let deltas = kernels::noise_deltas(&mut rng, PIXELS * 4, max);
+        compare(
+            &format!("noise/{max}"),
+            &image,
+            |d| kernels::noise_scalar(d, &deltas),
+            |d| kernels::noise(d, &deltas),
+        );
+    }
+
+    let pattern = [0x5a, 0x13, 0xff, 0x00, 0x81];
+    compare(
+        "xor",
+        &image,
+        |d| kernels::xor_pattern_scalar(d, &pattern, 3),
+        |d| kernels::xor_pattern(d, &pattern, 3),
+    );
+
+    for mode in 0..5 {
+        for amount in [0.0, 0.3, 1.0] {
+            let amount = kernels::blend_amount(amount);
+            compare(
+                &format!("blend/{mode}/{amount}"),
+                &image,
+                |d| kernels::blend_scalar(d, &secondary, mode, amount),
+                |d| kernels::blend(d, &secondary, mode, amount),
+            );
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// Image statistics for the UI: histograms, per-channel moments, entropy and edge density,
+// and before/after difference metrics (MSE, PSNR, SSIM) to measure how much an effect changed.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::masks;
+use crate::yuv::rgb_to_ycbcr;
+
+// Sobel magnitude above which a pixel counts as an edge, relative to a full black to white
+// step (a raw response of 64 on 0-255 luminance)
+const EDGE_THRESHOLD: f32 = 64.0 / 1020.0;
+
+// SSIM window size and step between windows
+const SSIM_WINDOW: usize = 8;
+const SSIM_STEP: usize = 4;
+
+#[derive(Serialize)]
+pub struct ChannelStats {
+    histogram: Vec<u32>, // 256 bins
+    mean: f64,
+    variance: f64,
+    entropy: f64,        // Shannon entropy in bits (0-8)
+    min: u8,
+    max: u8,
+}
+
+#[derive(Serialize)]
+pub struct ImageAnalysis {
+    red: ChannelStats,
+    green: ChannelStats,
+    blue: ChannelStats,
+    alpha: ChannelStats,
+    luminance: ChannelStats, // BT.601 luma
+    low: u8,                 // 1st percentile of luminance, a black point for auto-leveling
+    median: u8,
+    high: u8,                // 99th percentile of luminance, a white point for auto-leveling
+    edge_density: f64,       // Fraction of pixels on an edge (0.0-1.0)
+}
+
+#[derive(Serialize)]
+pub struct ImageDifference {
+    mse: f64,               // Mean squared error over RGB
+    mse_channels: [f64; 3], // Per channel R, G, B
+    psnr: Option<f64>,      // Peak signal-to-noise ratio in dB, None for identical images
+    ssim: f64,              // Structural similarity of the luminance (1.0 = identical)
+}
+
+pub fn luminance(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4).map(|pixel| rgb_to_ycbcr(pixel[0], pixel[1], pixel[2])[0]).collect()
+}
+
+pub fn histogram(values: impl Iterator<Item = u8>) -> Vec<u32> {
+    let mut histogram = vec![0; 256];
+    for value in values {
+        histogram[value as usize] += 1;
+    }
+    histogram
+}
+
+// Smallest value with at least `fraction` of the samples at or below it
+pub fn percentile(histogram: &[u32], fraction: f64) -> u8 {
+    let total: u64 = histogram.iter().map(|&count| count as u64).sum();
+    let target = (fraction.clamp(0.0, 1.0) * total as f64).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (value, &count) in histogram.iter().enumerate() {
+        seen += count as u64;
+        if seen >= target {
+            return value as u8;
+        }
+    }
+    255
+}
+
+// Otsu's method: the value that best splits the histogram into two classes (maximum
+// between-class variance). Values at or below it form the darker class.
+pub fn otsu(histogram: &[u32]) -> u8 {
+    let total: f64 = histogram.iter().map(|&count| count as f64).sum();
+    let sum_all: f64 = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum();
+    let (mut weight_low, mut sum_low) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (0, -1.0);
+    for (value, &count) in histogram.iter().enumerate() {
+        weight_low += count as f64;
+        sum_low += value as f64 * count as f64;
+        let weight_high = total - weight_low;
+        if weight_low == 0.0 || weight_high == 0.0 { continue; }
+        let mean_low = sum_low / weight_low;
+        let mean_high = (sum_all - sum_low) / weight_high;
+        let variance = weight_low * weight_high * (mean_low - mean_high).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = value;
+        }
+    }
+    best as u8
+}
+
+fn channel_stats(histogram: Vec<u32>) -> ChannelStats {
+    let total: f64 = histogram.iter().map(|&count| count as f64).sum();
+    if total == 0.0 {
+        return ChannelStats { histogram, mean: 0.0, variance: 0.0, entropy: 0.0, min: 0, max: 0 };
+    }
+    let mean = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum::<f64>() / total;
+    let variance = histogram.iter().enumerate()
+        .map(|(value, &count)| (value as f64 - mean).powi(2) * count as f64)
+        .sum::<f64>() / total;
+    let entropy = histogram.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    let min = histogram.iter().position(|&count| count > 0).unwrap_or(0) as u8;
+    let max = histogram.iter().rposition(|&count| count > 0).unwrap_or(0) as u8;
+    ChannelStats { histogram, mean, variance, entropy, min, max }
+}
+
+// Fraction of interior pixels whose Sobel gradient magnitude exceeds EDGE_THRESHOLD
+fn edge_density(luma: &[u8], width: usize) -> f64 {
+    if width < 3 || luma.len() / width < 3 { return 0.0; }
+    let height = luma.len() / width;
+    let luma: Vec<f32> = luma[..width * height].iter().map(|&value| value as f32 / 255.0).collect();
+    let (gx, gy) = masks::gradients(&luma, width, height);
+    let mut edges = 0usize;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let i = y * width + x;
+            if gx[i].hypot(gy[i]) > EDGE_THRESHOLD {
+                edges += 1;
+            }
+        }
+    }
+    edges as f64 / ((width - 2) * (height - 2)) as f64
+}
+
+pub fn analyze(data: &[u8], width: u32) -> ImageAnalysis {
+    let channel = |c: usize| channel_stats(histogram(data.chunks_exact(4).map(|pixel| pixel[c])));
+    let luma = luminance(data);
+    let luminance = channel_stats(histogram(luma.iter().copied()));
+    ImageAnalysis {
+        red: channel(0),
+        green: channel(1),
+        blue: channel(2),
+        alpha: channel(3),
+        low: percentile(&luminance.histogram, 0.01),
+        median: percentile(&luminance.histogram, 0.5),
+        high: percentile(&luminance.histogram, 0.99),
+        edge_density: edge_density(&luma, width as usize),
+        luminance,
+    }
+}
+
+// Mean SSIM over overlapping square windows of the luminance, with the standard constants
+fn ssim(a: &[u8], b: &[u8], width: usize) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    let height = a.len().checked_div(width).unwrap_or(0);
+    // Images smaller than a window are compared as a single window
+    let window_w = SSIM_WINDOW.min(width);
+    let window_h = SSIM_WINDOW.min(height);
+    if window_w == 0 || window_h == 0 { return 1.0; }
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y0 in (0..=height - window_h).step_by(SSIM_STEP) {
+        for x0 in (0..=width - window_w).step_by(SSIM_STEP) {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for y in y0..y0 + window_h {
+                for x in x0..x0 + window_w {
+                    let (va, vb) = (a[y * width + x] as f64, b[y * width + x] as f64);
+                    sum_a += va;
+                    sum_b += vb;
+                    sum_aa += va * va;
+                    sum_bb += vb * vb;
+                    sum_ab += va * vb;
+                }
+            }
+            let n = (window_w * window_h) as f64;
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+pub fn compare(before: &[u8], after: &[u8], width: u32) -> Result<ImageDifference, String> {
+    if before.len() != after.len() {
+        return Err("Images must have the same size".to_string());
+    }
+    let pixels = (before.len() / 4).max(1) as f64;
+    let mut squared = [0.0; 3];
+    for (a, b) in before.chunks_exact(4).zip(after.chunks_exact(4)) {
+        for c in 0..3 {
+            squared[c] += (a[c] as f64 - b[c] as f64).powi(2);
+        }
+    }
+    let mse_channels = squared.map(|sum| sum / pixels);
+    let mse = mse_channels.iter().sum::<f64>() / 3.0;
+    Ok(ImageDifference {
+        mse,
+        mse_channels,
+        psnr: (mse > 0.0).then(|| 10.0 * (255.0 * 255.0 / mse).log10()),
+        ssim: ssim(&luminance(before), &luminance(after), width as usize),
+    })
+}
+
+// Histograms and statistics for RGBA data (e.g. `imageData.data`)
+#[wasm_bindgen]
+pub fn analyze_image(data: &[u8], width: u32) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&analyze(data, width))?)
+}
+
+// How much an effect changed an image: MSE, PSNR and SSIM between two same-sized images
+#[wasm_bindgen]
+pub fn compare_images(before: &[u8], after: &[u8], width: u32) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&compare(before, after, width)?)?)
+}
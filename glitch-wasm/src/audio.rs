@@ -0,0 +1,486 @@
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::fft::{fft, Complex};
+use crate::modulation::{self, ModulationTarget};
+use crate::GlitchOptions;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FrequencyBand {
+    pub(crate) low: f64,  // Lower edge in Hz
+    pub(crate) high: f64, // Upper edge in Hz
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AudioAnalyzerOptions {
+    pub(crate) sample_rate: Option<f64>,            // Default: 44100, taken from the file for WAV analysis
+    pub(crate) fft_size: Option<usize>,             // Analysis window, rounded up to a power of two (default: 2048)
+    pub(crate) bands: Option<Vec<FrequencyBand>>,   // Default: bass 20-250, mid 250-4000, treble 4000-16000
+    pub(crate) attack: Option<f64>,                 // Envelope attack time in seconds (default: 0.01)
+    pub(crate) release: Option<f64>,                // Envelope release time in seconds (default: 0.25)
+    pub(crate) onset_threshold: Option<f64>,        // Flux must exceed the recent average by this factor (default: 1.5)
+    pub(crate) onset_cooldown: Option<f64>,         // Minimum seconds between onsets (default: 0.1)
+    pub(crate) db_range: Option<f64>,               // Dynamic range mapped to 0.0-1.0 band levels (default: 60)
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct AudioFeatures {
+    pub(crate) time: f64,           // Position of the end of the analysis window in seconds
+    pub(crate) rms: f64,            // Smoothed RMS level (0.0-1.0)
+    pub(crate) bands: Vec<f64>,     // Smoothed band levels (0.0-1.0)
+    pub(crate) flux: f64,           // Spectral flux of this frame
+    pub(crate) onset: bool,         // An onset was detected in this frame
+    pub(crate) onset_strength: f64, // Decaying onset envelope (1.0 on the onset frame)
+    pub(crate) bpm: Option<f64>,    // Tempo estimated from recent onset intervals
+}
+
+// Feature an audio mapping reads. Unknown names are rejected when options are parsed,
+// like unknown targets.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub enum AudioSource {
+    Rms,
+    Flux,
+    Onset,
+    Band(usize),
+}
+
+impl TryFrom<String> for AudioSource {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        match name.as_str() {
+            "rms" => Ok(Self::Rms),
+            "flux" => Ok(Self::Flux),
+            "onset" => Ok(Self::Onset),
+            _ => name.strip_prefix("band.")
+                .and_then(|index| index.parse().ok())
+                .map(Self::Band)
+                .ok_or_else(|| format!("Unknown audio source \"{}\"", name)),
+        }
+    }
+}
+
+impl From<AudioSource> for String {
+    fn from(source: AudioSource) -> String {
+        match source {
+            AudioSource::Rms => "rms".into(),
+            AudioSource::Flux => "flux".into(),
+            AudioSource::Onset => "onset".into(),
+            AudioSource::Band(index) => format!("band.{}", index),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AudioMapping {
+    pub(crate) source: AudioSource, // "rms", "flux", "onset" or "band.N"
+    pub(crate) target: ModulationTarget,      // Parameter path, same as modulation routes
+    pub(crate) depth: f64,
+    pub(crate) offset: Option<f64>,
+    pub(crate) min: Option<f64>,
+    pub(crate) max: Option<f64>,
+}
+
+// Number of past frames used for the adaptive onset threshold
+const FLUX_HISTORY: usize = 43;
+// Number of onset intervals kept for tempo estimation
+const ONSET_HISTORY: usize = 16;
+
+#[wasm_bindgen]
+pub struct AudioAnalyzer {
+    options: AudioAnalyzerOptions,
+    fft_size: usize,
+    window: Vec<f64>,
+    samples: Vec<f32>,
+    previous_spectrum: Vec<f64>,
+    flux_history: Vec<f64>,
+    onset_times: Vec<f64>,
+    features: AudioFeatures,
+    total_samples: u64,
+}
+
+// Smoothing coefficient for a one-pole follower over `dt` seconds
+fn follow(current: f64, target: f64, attack: f64, release: f64, dt: f64) -> f64 {
+    let tau = if target > current { attack } else { release };
+    if tau <= 0.0 { return target; }
+    let coefficient = (-dt / tau).exp();
+    target + (current - target) * coefficient
+}
+
+impl AudioAnalyzer {
+    fn from_options(options: AudioAnalyzerOptions) -> Self {
+        let fft_size = options.fft_size.unwrap_or(2048).clamp(64, 32768).next_power_of_two();
+        // Hann window
+        let window = (0..fft_size)
+            .map(|i| 0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / fft_size as f64).cos())
+            .collect();
+        let band_count = options.bands.as_ref().map_or(3, |b| b.len());
+        Self {
+            options,
+            fft_size,
+            window,
+            samples: Vec::with_capacity(fft_size),
+            previous_spectrum: vec![0.0; fft_size / 2],
+            flux_history: Vec::with_capacity(FLUX_HISTORY),
+            onset_times: Vec::with_capacity(ONSET_HISTORY + 1),
+            features: AudioFeatures { bands: vec![0.0; band_count], ..Default::default() },
+            total_samples: 0,
+        }
+    }
+
+    fn bands(&self) -> Vec<FrequencyBand> {
+        self.options.bands.clone().unwrap_or_else(|| vec![
+            FrequencyBand { low: 20.0, high: 250.0 },
+            FrequencyBand { low: 250.0, high: 4000.0 },
+            FrequencyBand { low: 4000.0, high: 16000.0 },
+        ])
+    }
+
+    // Feed a block of mono samples and analyze the most recent window
+    fn analyze_block(&mut self, block: &[f32]) -> AudioFeatures {
+        let sample_rate = self.options.sample_rate.unwrap_or(44100.0).max(1.0);
+        let attack = self.options.attack.unwrap_or(0.01);
+        let release = self.options.release.unwrap_or(0.25);
+        let db_range = self.options.db_range.unwrap_or(60.0).max(1.0);
+        let dt = block.len() as f64 / sample_rate;
+
+        self.total_samples += block.len() as u64;
+        self.samples.extend_from_slice(block);
+        if self.samples.len() > self.fft_size {
+            let excess = self.samples.len() - self.fft_size;
+            self.samples.drain(..excess);
+        }
+
+        // RMS of the new block
+        let block_rms = if block.is_empty() {
+            0.0
+        } else {
+            (block.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / block.len() as f64).sqrt()
+        };
+
+        // Windowed spectrum of the latest samples, zero-padded at the front while filling up
+        let mut buffer = vec![Complex::default(); self.fft_size];
+        let pad = self.fft_size - self.samples.len();
+        for (i, &sample) in self.samples.iter().enumerate() {
+            buffer[pad + i] = Complex::new(sample as f64 * self.window[pad + i], 0.0);
+        }
+        fft(&mut buffer, false);
+
+        let half = self.fft_size / 2;
+        let normalize = 4.0 / self.fft_size as f64; // Hann window gain of 0.5, one-sided spectrum
+        let spectrum: Vec<f64> = buffer[..half].iter().map(|c| c.norm() * normalize).collect();
+
+        // Spectral flux: sum of magnitude increases since the previous frame
+        let flux: f64 = spectrum.iter()
+            .zip(&self.previous_spectrum)
+            .map(|(&now, &before)| (now - before).max(0.0))
+            .sum();
+        self.previous_spectrum = spectrum.clone();
+
+        // Band levels in dB mapped to 0.0-1.0
+        let bin_hz = sample_rate / self.fft_size as f64;
+        let levels: Vec<f64> = self.bands().iter().map(|band| {
+            let low = ((band.low / bin_hz).floor() as usize).min(half - 1);
+            let high = ((band.high / bin_hz).ceil() as usize).clamp(low + 1, half);
+            let mean = spectrum[low..high].iter().sum::<f64>() / (high - low) as f64;
+            let db = 20.0 * mean.max(1e-12).log10();
+            ((db + db_range) / db_range).clamp(0.0, 1.0)
+        }).collect();
+
+        let time = self.total_samples as f64 / sample_rate;
+
+        // Adaptive onset detection against the recent flux average
+        let average = if self.flux_history.is_empty() {
+            f64::INFINITY
+        } else {
+            self.flux_history.iter().sum::<f64>() / self.flux_history.len() as f64
+        };
+        let cooldown = self.options.onset_cooldown.unwrap_or(0.1);
+        let since_last = self.onset_times.last().map_or(f64::INFINITY, |&t| time - t);
+        let onset = flux > average * self.options.onset_threshold.unwrap_or(1.5)
+            && flux > 1e-4
+            && since_last >= cooldown;
+
+        if self.flux_history.len() == FLUX_HISTORY {
+            self.flux_history.remove(0);
+        }
+        self.flux_history.push(flux);
+
+        if onset {
+            self.onset_times.push(time);
+            if self.onset_times.len() > ONSET_HISTORY + 1 {
+                self.onset_times.remove(0);
+            }
+        }
+
+        let previous = &self.features;
+        let bands = levels.iter().enumerate()
+            .map(|(i, &level)| follow(previous.bands.get(i).copied().unwrap_or(0.0), level, attack, release, dt))
+            .collect();
+
+        self.features = AudioFeatures {
+            time,
+            rms: follow(previous.rms, block_rms.min(1.0), attack, release, dt),
+            bands,
+            flux,
+            onset,
+            onset_strength: if onset { 1.0 } else { follow(previous.onset_strength, 0.0, attack, release, dt) },
+            bpm: self.estimate_bpm(),
+        };
+        self.features.clone()
+    }
+
+    // Median interval between recent onsets, folded into 60-200 BPM
+    fn estimate_bpm(&self) -> Option<f64> {
+        if self.onset_times.len() < 4 { return None; }
+        let mut intervals: Vec<f64> = self.onset_times.windows(2).map(|w| w[1] - w[0]).collect();
+        intervals.sort_by(|a, b| a.total_cmp(b));
+        let median = intervals[intervals.len() / 2];
+        if median <= 0.0 { return None; }
+        let mut bpm = 60.0 / median;
+        while bpm < 60.0 { bpm *= 2.0; }
+        while bpm > 200.0 { bpm /= 2.0; }
+        Some(bpm)
+    }
+}
+
+#[wasm_bindgen]
+impl AudioAnalyzer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(options_js: JsValue) -> Result<AudioAnalyzer, JsValue> {
+        let options: AudioAnalyzerOptions = serde_wasm_bindgen::from_value(options_js)?;
+        Ok(Self::from_options(options))
+    }
+
+    // Analyze a block of mono PCM samples (-1.0..1.0) and return the current features
+    #[wasm_bindgen]
+    pub fn process(&mut self, samples: &[f32]) -> Result<JsValue, JsValue> {
+        let features = self.analyze_block(samples);
+        Ok(serde_wasm_bindgen::to_value(&features)?)
+    }
+
+    #[wasm_bindgen]
+    pub fn reset(&mut self) {
+        *self = Self::from_options(self.options.clone());
+    }
+}
+
+// Value of a mapping source in the features, or None for a band the analysis doesn't have
+fn source_value(features: &AudioFeatures, source: AudioSource) -> Option<f64> {
+    match source {
+        AudioSource::Rms => Some(features.rms),
+        AudioSource::Flux => Some(features.flux.min(1.0)),
+        AudioSource::Onset => Some(features.onset_strength),
+        AudioSource::Band(index) => features.bands.get(index).copied(),
+    }
+}
+
+// Drive parameters from audio features through the mapping table
+pub(crate) fn apply_audio(options: &mut GlitchOptions) {
+    let (features, mappings) = match (options.audio.take(), options.audio_mapping.take()) {
+        (Some(features), Some(mappings)) => (features, mappings),
+        (features, mappings) => {
+            options.audio = features;
+            options.audio_mapping = mappings;
+            return;
+        }
+    };
+
+    for mapping in &mappings {
+        if let Some(value) = source_value(&features, mapping.source) {
+            modulation::modulate_target(options, mapping.target, value, mapping.depth,
+                                        mapping.offset, mapping.min, mapping.max);
+        }
+    }
+
+    options.audio = Some(features);
+    options.audio_mapping = Some(mappings);
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+}
+
+// Decode a PCM (8/16/24/32-bit integer) or 32-bit float WAV file to mono samples
+pub fn decode_wav(bytes: &[u8]) -> Result<(Vec<f32>, u32), String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a RIFF/WAVE file".into());
+    }
+
+    let mut pos = 12;
+    let mut format = None;
+    let mut data = None;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = read_u32(bytes, pos + 4) as usize;
+        let body = pos + 8;
+        let end = body.saturating_add(size);
+        if end > bytes.len() {
+            // Streamed and truncated files can't give the real data size (streams write
+            // 0xFFFFFFFF), so the samples run to the end of the file. Anything else that
+            // overruns is trailing junk after the audio.
+            if id == b"data" {
+                data = Some(&bytes[body..]);
+            }
+            break;
+        }
+        match id {
+            b"fmt " if size >= 16 && end - body >= 16 => {
+                let mut tag = read_u16(bytes, body);
+                // WAVE_FORMAT_EXTENSIBLE stores the real format at the start of the sub-format GUID
+                if tag == 0xFFFE && end - body >= 26 {
+                    tag = read_u16(bytes, body + 24);
+                }
+                format = Some((
+                    tag,
+                    read_u16(bytes, body + 2),  // Channels
+                    read_u32(bytes, body + 4),  // Sample rate
+                    read_u16(bytes, body + 14), // Bits per sample
+                ));
+            }
+            b"data" => data = Some(&bytes[body..end]),
+            _ => {}
+        }
+        pos = end.saturating_add(size & 1); // Chunks are word aligned
+    }
+
+    let (tag, channels, sample_rate, bits) = format.ok_or("Missing fmt chunk")?;
+    let data = data.ok_or("Missing data chunk")?;
+    let channels = channels.max(1) as usize;
+    let bytes_per_sample = (bits as usize).div_ceil(8);
+    let float = match tag {
+        1 => false,
+        3 => true,
+        _ => return Err(format!("Unsupported WAV format {}", tag)),
+    };
+
+    let decode = |s: &[u8]| -> f32 {
+        match (bytes_per_sample, float) {
+            (1, _) => (s[0] as f32 - 128.0) / 128.0,
+            (2, _) => i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0,
+            (3, _) => (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8_388_608.0,
+            (4, true) => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+            (4, false) => i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0,
+            _ => 0.0,
+        }
+    };
+
+    let frame_bytes = bytes_per_sample * channels;
+    if frame_bytes == 0 || bytes_per_sample > 4 {
+        return Err(format!("Unsupported bit depth {}", bits));
+    }
+    let samples = data.chunks_exact(frame_bytes)
+        .map(|frame| frame.chunks_exact(bytes_per_sample).map(decode).sum::<f32>() / channels as f32)
+        .collect();
+
+    Ok((samples, sample_rate))
+}
+
+// Upper bound for offline analysis, about 4.6 hours at 60 fps, so a bad frame rate can't
+// exhaust memory
+const MAX_FRAMES: usize = 1_000_000;
+
+// Analyze a whole decoded signal, producing exactly one feature set per video frame
+pub fn analyze_offline(samples: &[f32], options: AudioAnalyzerOptions, fps: f64) -> Result<Vec<AudioFeatures>, String> {
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err("fps must be a positive number".into());
+    }
+    let sample_rate = options.sample_rate.unwrap_or(44100.0).max(1.0);
+    let duration = samples.len() as f64 / sample_rate;
+    let frames = (duration * fps).ceil();
+    if frames > MAX_FRAMES as f64 {
+        return Err(format!("{} frames is more than the maximum of {}", frames, MAX_FRAMES));
+    }
+    let mut analyzer = AudioAnalyzer::from_options(options);
+
+    Ok((0..frames as usize).map(|frame| {
+        // Frame boundaries are rounded from absolute time so they never drift
+        let start = ((frame as f64 / fps) * sample_rate).round() as usize;
+        let end = (((frame + 1) as f64 / fps) * sample_rate).round() as usize;
+        analyzer.analyze_block(&samples[start.min(samples.len())..end.min(samples.len())])
+    }).collect())
+}
+
+// Decode a WAV file and return one feature set per frame at the given frame rate.
+// The options' sample rate is taken from the file.
+#[wasm_bindgen]
+pub fn analyze_wav(wav: &[u8], fps: f64, options_js: JsValue) -> Result<JsValue, JsValue> {
+    let mut options: AudioAnalyzerOptions = serde_wasm_bindgen::from_value(options_js)?;
+    let (samples, sample_rate) = decode_wav(wav).map_err(|e| JsValue::from_str(&e))?;
+    options.sample_rate = Some(sample_rate as f64);
+    let features = analyze_offline(&samples, options, fps).map_err(|e| JsValue::from_str(&e))?;
+    Ok(serde_wasm_bindgen::to_value(&features)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 16-bit mono WAV with the given chunks after the header
+    fn wav(chunks: &[(&[u8; 4], u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        for (id, size, body) in chunks {
+            bytes.extend_from_slice(*id);
+            bytes.extend_from_slice(&size.to_le_bytes());
+            bytes.extend_from_slice(body);
+        }
+        bytes
+    }
+
+    const FMT: [u8; 16] = [1, 0, 1, 0, 0x44, 0xAC, 0, 0, 0x88, 0x58, 1, 0, 2, 0, 16, 0];
+    const SAMPLES: [u8; 6] = [0, 0, 0, 0x40, 0, 0xC0];
+
+    #[test]
+    fn decodes_complete_file() {
+        let bytes = wav(&[(b"fmt ", 16, &FMT), (b"data", 6, &SAMPLES)]);
+        assert_eq!(decode_wav(&bytes), Ok((vec![0.0, 0.5, -0.5], 44100)));
+    }
+
+    #[test]
+    fn streamed_data_runs_to_end_of_file() {
+        let bytes = wav(&[(b"fmt ", 16, &FMT), (b"data", u32::MAX, &SAMPLES)]);
+        assert_eq!(decode_wav(&bytes), Ok((vec![0.0, 0.5, -0.5], 44100)));
+    }
+
+    #[test]
+    fn truncated_data_keeps_whole_frames() {
+        let bytes = wav(&[(b"fmt ", 16, &FMT), (b"data", 100, &SAMPLES[..5])]);
+        assert_eq!(decode_wav(&bytes), Ok((vec![0.0, 0.5], 44100)));
+    }
+
+    #[test]
+    fn stops_at_overrunning_chunk_after_data() {
+        let bytes = wav(&[(b"fmt ", 16, &FMT), (b"data", 6, &SAMPLES), (b"LIST", 64, b"INFO")]);
+        assert_eq!(decode_wav(&bytes), Ok((vec![0.0, 0.5, -0.5], 44100)));
+    }
+
+    #[test]
+    fn overrunning_chunk_before_data_leaves_it_missing() {
+        let bytes = wav(&[(b"fmt ", 16, &FMT), (b"LIST", 64, b"INFO"), (b"data", 6, &SAMPLES)]);
+        assert_eq!(decode_wav(&bytes), Err("Missing data chunk".to_string()));
+    }
+
+    #[test]
+    fn rejects_bad_frame_rates() {
+        let options: AudioAnalyzerOptions = serde_json::from_str("{}").unwrap();
+        for fps in [0.0, -30.0, f64::NAN, f64::INFINITY, 1e12] {
+            assert!(analyze_offline(&[0.0; 44100], options.clone(), fps).is_err(), "fps {fps}");
+        }
+        assert_eq!(analyze_offline(&[0.0; 44100], options, 30.0).map(|f| f.len()), Ok(30));
+    }
+
+    #[test]
+    fn rejects_unknown_sources() {
+        let source = |name: &str| serde_json::from_value::<AudioSource>(serde_json::json!(name));
+        assert_eq!(source("band.2").ok(), Some(AudioSource::Band(2)));
+        assert_eq!(serde_json::to_value(AudioSource::Band(2)).unwrap(), "band.2");
+        for name in ["volume", "band.", "band.x", "RMS"] {
+            assert!(source(name).is_err(), "{name}");
+        }
+    }
+}
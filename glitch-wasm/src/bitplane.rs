@@ -0,0 +1,126 @@
+use rand::Rng;
+use rand::rngs::SmallRng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BitSwap {
+    pub(crate) channel_a: usize, // 0=R, 1=G, 2=B, 3=A
+    pub(crate) bit_a: u8,        // 0 (least significant) - 7 (most significant)
+    pub(crate) channel_b: usize,
+    pub(crate) bit_b: u8,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Region {
+    pub(crate) x: f64,      // Left edge relative to image width (0.0-1.0)
+    pub(crate) y: f64,      // Top edge relative to image height (0.0-1.0)
+    pub(crate) width: f64,  // Relative width (0.0-1.0)
+    pub(crate) height: f64, // Relative height (0.0-1.0)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BitPlaneOptions {
+    pub(crate) operation: usize,             // 0=isolate, 1=shift, 2=rotate, 3=swap, 4=invert, 5=shuffle
+    pub(crate) channels: Option<Vec<usize>>, // Channels to process (default: RGB)
+    pub(crate) planes: Option<Vec<u8>>,      // Bit planes affected (0-7, default: all)
+    pub(crate) shift: Option<i32>,           // Shift/rotate amount, positive=towards the MSB (default: 1)
+    pub(crate) swaps: Option<Vec<BitSwap>>,  // Plane pairs exchanged by the swap operation
+    pub(crate) regions: Option<Vec<Region>>, // Only process pixels inside these regions (default: whole image)
+    pub(crate) invert_regions: bool,         // Process pixels outside the regions instead
+}
+
+fn plane_mask(planes: &Option<Vec<u8>>) -> u8 {
+    match planes {
+        Some(planes) => planes.iter().filter(|&&p| p < 8).fold(0, |mask, &p| mask | (1 << p)),
+        None => 0xFF,
+    }
+}
+
+fn in_regions(regions: &[Region], x: usize, y: usize, width: usize, height: usize) -> bool {
+    let fx = x as f64 / width as f64;
+    let fy = y as f64 / height as f64;
+    regions.iter().any(|r| fx >= r.x && fx < r.x + r.width && fy >= r.y && fy < r.y + r.height)
+}
+
+// Move every bit to the position given by the permutation (bit i goes to permutation[i])
+fn permute_bits(value: u8, permutation: &[u8; 8]) -> u8 {
+    (0..8).fold(0, |out, bit| out | (((value >> bit) & 1) << permutation[bit]))
+}
+
+pub fn bit_plane(data: &mut [u8], width: u32, options: &BitPlaneOptions, rng: &mut SmallRng) {
+    let width = width as usize;
+    if width == 0 { return; }
+    let height = data.len() / 4 / width;
+    if height == 0 { return; }
+
+    let channels: Vec<usize> = options.channels.clone()
+        .unwrap_or_else(|| vec![0, 1, 2])
+        .into_iter()
+        .filter(|&ch| ch < 4)
+        .collect();
+    let mask = plane_mask(&options.planes);
+    let shift = options.shift.unwrap_or(1);
+
+    // One random plane permutation per channel, chosen up front so it is the same for every pixel
+    let permutations: Vec<[u8; 8]> = (0..4).map(|_| {
+        let mut permutation = [0, 1, 2, 3, 4, 5, 6, 7];
+        // Only shuffle planes inside the mask among themselves
+        let selected: Vec<usize> = (0..8).filter(|&bit| mask & (1 << bit) != 0).collect();
+        for i in (1..selected.len()).rev() {
+            let j = rng.gen_range(0..i + 1);
+            permutation.swap(selected[i], selected[j]);
+        }
+        permutation
+    }).collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            if let Some(regions) = &options.regions {
+                if in_regions(regions, x, y, width, height) == options.invert_regions {
+                    continue;
+                }
+            }
+
+            let idx = (y * width + x) * 4;
+            let pixel = &mut data[idx..idx + 4];
+
+            if options.operation == 3 {
+                // Swaps read the original pixel so chained swaps don't feed into each other
+                let original = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                for swap in options.swaps.iter().flatten() {
+                    if swap.channel_a > 3 || swap.channel_b > 3 || swap.bit_a > 7 || swap.bit_b > 7 {
+                        continue;
+                    }
+                    let a = (original[swap.channel_a] >> swap.bit_a) & 1;
+                    let b = (original[swap.channel_b] >> swap.bit_b) & 1;
+                    pixel[swap.channel_a] = (pixel[swap.channel_a] & !(1 << swap.bit_a)) | (b << swap.bit_a);
+                    pixel[swap.channel_b] = (pixel[swap.channel_b] & !(1 << swap.bit_b)) | (a << swap.bit_b);
+                }
+                continue;
+            }
+
+            for &ch in &channels {
+                let value = pixel[ch];
+                pixel[ch] = match options.operation {
+                    // Keep only the selected planes, stretched back to the full range
+                    0 => if mask == 0 { 0 } else { ((value & mask) as u16 * 255 / mask as u16) as u8 },
+                    1 => {
+                        let shifted = if shift >= 0 {
+                            value.checked_shl(shift as u32).unwrap_or(0)
+                        } else {
+                            value.checked_shr(shift.unsigned_abs()).unwrap_or(0)
+                        };
+                        (shifted & mask) | (value & !mask)
+                    }
+                    2 => {
+                        let rotated = value.rotate_left(shift.rem_euclid(8) as u32);
+                        (rotated & mask) | (value & !mask)
+                    }
+                    4 => value ^ mask,
+                    5 => permute_bits(value, &permutations[ch]),
+                    _ => value,
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// Blend modes and Porter-Duff compositing shared by image blend and the layer compositor,
+// following the W3C Compositing and Blending spec. Colors are 0.0-1.0 and not premultiplied.
+// Separable modes work per channel; hue, saturation, color, luminosity and darker/lighter
+// color combine whole colors.
+//
+// Modes: 0=normal, 1=difference, 2=multiply, 3=screen, 4=overlay, 5=add (linear dodge),
+// 6=exclusion, 7=hue, 8=color, 9=luminosity, 10=soft light, 11=hard light, 12=color dodge,
+// 13=color burn, 14=linear burn, 15=linear light, 16=vivid light, 17=pin light, 18=hard mix,
+// 19=darken, 20=lighten, 21=subtract, 22=divide, 23=saturation, 24=darker color,
+// 25=lighter color
+//
+// Operators: 0=source-over, 1=source-atop, 2=source-in, 3=source-out, 4=destination-over,
+// 5=destination-atop, 6=destination-in, 7=destination-out, 8=xor, 9=copy, 10=lighter, 11=clear
+
+pub const MODES: usize = 26;
+
+// How a source is combined with a backdrop
+#[derive(Clone, Copy)]
+pub struct Compositing {
+    pub mode: usize,
+    pub operator: usize,
+    pub linear: bool, // Blend in linear light instead of on the sRGB-encoded values
+}
+
+fn hard_light(b: f32, s: f32) -> f32 {
+    if s <= 0.5 {
+        b * 2.0 * s
+    } else {
+        let s = 2.0 * s - 1.0;
+        b + s - b * s
+    }
+}
+
+fn soft_light(b: f32, s: f32) -> f32 {
+    if s <= 0.5 {
+        b - (1.0 - 2.0 * s) * b * (1.0 - b)
+    } else {
+        let d = if b <= 0.25 { ((16.0 * b - 12.0) * b + 4.0) * b } else { b.sqrt() };
+        b + (2.0 * s - 1.0) * (d - b)
+    }
+}
+
+fn color_dodge(b: f32, s: f32) -> f32 {
+    if b <= 0.0 {
+        0.0
+    } else if s >= 1.0 {
+        1.0
+    } else {
+        (b / (1.0 - s)).min(1.0)
+    }
+}
+
+fn color_burn(b: f32, s: f32) -> f32 {
+    if b >= 1.0 {
+        1.0
+    } else if s <= 0.0 {
+        0.0
+    } else {
+        1.0 - ((1.0 - b) / s).min(1.0)
+    }
+}
+
+fn separable(b: f32, s: f32, mode: usize) -> f32 {
+    match mode {
+        1 => (b - s).abs(),
+        2 => b * s,
+        3 => b + s - b * s,
+        4 => hard_light(s, b),
+        5 => (b + s).min(1.0),
+        6 => b + s - 2.0 * b * s,
+        10 => soft_light(b, s),
+        11 => hard_light(b, s),
+        12 => color_dodge(b, s),
+        13 => color_burn(b, s),
+        14 => (b + s - 1.0).max(0.0),
+        15 => (b + 2.0 * s - 1.0).clamp(0.0, 1.0),
+        16 => if s <= 0.5 { color_burn(b, 2.0 * s) } else { color_dodge(b, 2.0 * s - 1.0) },
+        17 => if s <= 0.5 { b.min(2.0 * s) } else { b.max(2.0 * s - 1.0) },
+        18 => if b + s >= 1.0 { 1.0 } else { 0.0 },
+        19 => b.min(s),
+        20 => b.max(s),
+        21 => (b - s).max(0.0),
+        22 => if s <= 0.0 { if b > 0.0 { 1.0 } else { 0.0 } } else { (b / s).min(1.0) },
+        _ => s,
+    }
+}
+
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+// Bring a color back into gamut while keeping its luminosity
+fn clip_color(c: [f32; 3]) -> [f32; 3] {
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    c.map(|v| {
+        let mut v = v;
+        if n < 0.0 { v = l + (v - l) * l / (l - n); }
+        if x > 1.0 { v = l + (v - l) * (1.0 - l) / (x - l); }
+        v
+    })
+}
+
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    clip_color(c.map(|v| v + d))
+}
+
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let (min, max) = (c[0].min(c[1]).min(c[2]), c[0].max(c[1]).max(c[2]));
+    if max > min {
+        c.map(|v| (v - min) * s / (max - min))
+    } else {
+        [0.0; 3]
+    }
+}
+
+// Blend a source color onto a backdrop color
+pub fn blend(backdrop: [f32; 3], source: [f32; 3], mode: usize) -> [f32; 3] {
+    match mode {
+        7 => set_lum(set_sat(source, sat(backdrop)), lum(backdrop)),
+        8 => set_lum(source, lum(backdrop)),
+        9 => set_lum(backdrop, lum(source)),
+        23 => set_lum(set_sat(backdrop, sat(source)), lum(backdrop)),
+        24 => if lum(source) < lum(backdrop) { source } else { backdrop },
+        25 => if lum(source) > lum(backdrop) { source } else { backdrop },
+        _ => [0, 1, 2].map(|c| separable(backdrop[c], source[c], mode)),
+    }
+}
+
+// Porter-Duff factors for the source and the backdrop
+fn factors(operator: usize, alpha_s: f32, alpha_b: f32) -> (f32, f32) {
+    match operator {
+        1 => (alpha_b, 1.0 - alpha_s),
+        2 => (alpha_b, 0.0),
+        3 => (1.0 - alpha_b, 0.0),
+        4 => (1.0 - alpha_b, 1.0),
+        5 => (1.0 - alpha_b, alpha_s),
+        6 => (0.0, alpha_s),
+        7 => (0.0, 1.0 - alpha_s),
+        8 => (1.0 - alpha_b, 1.0 - alpha_s),
+        9 => (1.0, 0.0),
+        10 => (1.0, 1.0),
+        11 => (0.0, 0.0),
+        _ => (1.0, 1.0 - alpha_s),
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn unit(value: u8) -> f32 {
+    value as f32 / 255.0
+}
+
+fn byte(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn color(pixel: &[u8], linear: bool) -> [f32; 3] {
+    let c = [unit(pixel[0]), unit(pixel[1]), unit(pixel[2])];
+    if linear { c.map(srgb_to_linear) } else { c }
+}
+
+// Composite one source pixel onto a backdrop pixel. `coverage` (opacity times mask) scales
+// the source alpha; where the backdrop is transparent the source shows unblended.
+pub fn composite(backdrop: &mut [u8], source: &[u8], compositing: &Compositing, coverage: f32) {
+    if compositing.mode >= MODES { return; }
+    let alpha_s = unit(source[3]) * coverage.clamp(0.0, 1.0);
+    let alpha_b = unit(backdrop[3]);
+    let (fa, fb) = factors(compositing.operator, alpha_s, alpha_b);
+    // Nothing of the source lands and all of the backdrop stays
+    if alpha_s * fa == 0.0 && fb == 1.0 { return; }
+
+    let alpha = (alpha_s * fa + alpha_b * fb).min(1.0);
+    if alpha > 0.0 {
+        let b = color(backdrop, compositing.linear);
+        let s = color(source, compositing.linear);
+        let blended = blend(b, s, compositing.mode);
+        for c in 0..3 {
+            let mixed = (1.0 - alpha_b) * s[c] + alpha_b * blended[c];
+            let value = ((alpha_s * fa * mixed + alpha_b * fb * b[c]) / alpha).clamp(0.0, 1.0);
+            backdrop[c] = byte(if compositing.linear { linear_to_srgb(value) } else { value });
+        }
+    }
+    backdrop[3] = byte(alpha);
+}
+
+pub fn composite_row(row: &mut [u8], source: &[u8], compositing: &Compositing, coverage: f32) {
+    for (backdrop, source) in row.chunks_exact_mut(4).zip(source.chunks_exact(4)) {
+        composite(backdrop, source, compositing, coverage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernels;
+
+    // image_blend uses the fixed-point kernels on opaque rows and the compositor elsewhere,
+    // so both must give the same bytes wherever the kernels are used
+    #[test]
+    fn kernels_match_composite_at_full_strength() {
+        let backdrop: Vec<u8> = (0..=255u8).flat_map(|b| (0..=255u8).flat_map(move |_| [b, b, b, 255])).collect();
+        let source: Vec<u8> = (0..=255u8).flat_map(|_| (0..=255u8).flat_map(|s| [s, s, s, 255])).collect();
+        for mode in 0..=4 {
+            let mut expected = backdrop.clone();
+            composite_row(&mut expected, &source, &Compositing { mode, operator: 0, linear: false }, 1.0);
+            let mut actual = backdrop.clone();
+            kernels::blend(&mut actual, &source, mode, kernels::blend_amount(1.0));
+            assert_eq!(expected, actual, "mode {mode}: kernel and compositor output differ");
+        }
+    }
+}
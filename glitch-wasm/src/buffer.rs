@@ -0,0 +1,223 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+
+use js_sys::Uint8ClampedArray;
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+
+use crate::transform::{self, Edge};
+
+// RGBA image that lives in wasm memory. JS allocates it once, writes pixels through the
+// `data()` view and hands it to `GlitchEffect::apply_effects_to_buffer`, which works in
+// place instead of copying an ImageData in and out on every frame.
+#[wasm_bindgen]
+pub struct ImageBuffer {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+#[wasm_bindgen]
+impl ImageBuffer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            data: vec![0; width as usize * height as usize * 4],
+            width,
+            height,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Live view of the pixels, usable with `set()` and `new ImageData(view, width, height)`.
+    // Any allocation in wasm can grow memory and detach the view, so take a fresh one each
+    // frame rather than holding on to it.
+    pub fn data(&self) -> Uint8ClampedArray {
+        // SAFETY: the view is only valid until wasm memory grows or the buffer is dropped or
+        // resized; JS is expected to use it straight away, as documented above
+        unsafe { Uint8ClampedArray::view(&self.data) }
+    }
+
+    // Change dimensions, clearing the pixels. The old view is invalid afterwards.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.data.clear();
+        self.data.resize(width as usize * height as usize * 4, 0);
+    }
+}
+
+impl ImageBuffer {
+    pub(crate) fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+pub(crate) struct StoredImage {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    hash: OnceCell<String>, // Computed when first needed, since most images are never looked up by hash
+}
+
+impl StoredImage {
+    fn new(data: Vec<u8>, width: u32, height: u32) -> Self {
+        Self { data, width, height, hash: OnceCell::new() }
+    }
+
+    fn hash(&self) -> &str {
+        self.hash.get_or_init(|| image_hash(&self.data, self.width, self.height))
+    }
+}
+
+// Content hash recipes use to reference an image: SHA-256 over the width and height
+// (little-endian u32) followed by the RGBA pixels, as lowercase hex
+pub fn image_hash(data: &[u8], width: u32, height: u32) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(width.to_le_bytes());
+    hasher.update(height.to_le_bytes());
+    hasher.update(data);
+    hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Secondary images registered once and referenced by handle from effect options
+#[derive(Default)]
+pub(crate) struct ImageStore {
+    images: HashMap<u32, StoredImage>,
+    next_handle: u32,
+}
+
+impl ImageStore {
+    pub fn insert(&mut self, data: Vec<u8>, width: u32, height: u32) -> u32 {
+        self.next_handle += 1;
+        self.images.insert(self.next_handle, StoredImage::new(data, width, height));
+        self.next_handle
+    }
+
+    pub fn replace(&mut self, handle: u32, data: Vec<u8>, width: u32, height: u32) -> bool {
+        match self.images.get_mut(&handle) {
+            Some(image) => {
+                *image = StoredImage::new(data, width, height);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, handle: u32) -> bool {
+        self.images.remove(&handle).is_some()
+    }
+
+    pub fn get(&self, handle: u32, offset_x: i32, offset_y: i32) -> Option<SecondaryImage<'_>> {
+        let image = self.images.get(&handle)?;
+        SecondaryImage::new(&image.data, image.width, image.height, offset_x, offset_y)
+    }
+
+    pub fn hash(&self, handle: u32) -> Option<&str> {
+        self.images.get(&handle).map(StoredImage::hash)
+    }
+
+    // Registered image with the given content hash, for assets referenced by recipes
+    pub fn find(&self, hash: &str, offset_x: i32, offset_y: i32) -> Option<SecondaryImage<'_>> {
+        let image = self.images.values().find(|image| image.hash() == hash)?;
+        SecondaryImage::new(&image.data, image.width, image.height, offset_x, offset_y)
+    }
+}
+
+// Where a secondary image goes on the primary
+pub(crate) struct Placement {
+    pub fit: usize,    // 0=tile at native size, 1=stretch, 2=cover, 3=contain, 4=mirror tile at native size, 5=native size once (transparent around it)
+    pub scale: f64,    // Size multiplier on top of the fit
+    pub rotation: f64, // Degrees clockwise around the image's center
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub filter: usize, // Resampling filter, see transform.rs
+}
+
+// Primary pixel centers to secondary coordinates: u = a*x + b*y + e, v = c*x + d*y + f
+struct Mapping {
+    matrix: [f64; 6],
+    edge: Edge,
+    filter: usize,
+}
+
+// Borrowed secondary image, sampled with an offset and wrap-around tiling, or through a placement
+pub(crate) struct SecondaryImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    offset_x: i32,
+    offset_y: i32,
+    mapping: Option<Mapping>, // None samples at the plain offset
+}
+
+impl<'a> SecondaryImage<'a> {
+    // None unless the data matches the dimensions and the image is not empty
+    pub fn new(data: &'a [u8], width: u32, height: u32, offset_x: i32, offset_y: i32) -> Option<Self> {
+        if width == 0 || height == 0 || data.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+        Some(Self { data, width, height, offset_x, offset_y, mapping: None })
+    }
+
+    // Place the image on a primary of the given size. The offset still moves it in primary
+    // pixels; fitted images are centered, native-size ones start at the top left.
+    pub fn placed(mut self, placement: &Placement, width: usize, height: usize) -> Self {
+        let (w, h) = (self.width as f64, self.height as f64);
+        let (fit_x, fit_y) = (width as f64 / w, height as f64 / h);
+        let (sx, sy) = match placement.fit {
+            1 => (fit_x, fit_y),
+            2 => (fit_x.max(fit_y), fit_x.max(fit_y)),
+            3 => (fit_x.min(fit_y), fit_x.min(fit_y)),
+            _ => (1.0, 1.0),
+        };
+        let (edge, center) = match placement.fit {
+            1..=3 => (Edge::Transparent, Some((width as f64 / 2.0, height as f64 / 2.0))),
+            4 => (Edge::Mirror, None),
+            5 => (Edge::Transparent, None),
+            _ => (Edge::Tile, None),
+        };
+        let scale = placement.scale.max(1e-3);
+        let (sx, sy) = (sx * scale, sy * scale);
+        let identity = edge == Edge::Tile && scale == 1.0 && placement.rotation == 0.0
+            && !placement.flip_x && !placement.flip_y && placement.filter == 0;
+        if identity || width == 0 || height == 0 {
+            return self;
+        }
+
+        let (cx, cy) = center.unwrap_or((w * sx / 2.0, h * sy / 2.0));
+        let (sin, cos) = placement.rotation.to_radians().sin_cos();
+        let fx = if placement.flip_x { -1.0 } else { 1.0 };
+        let fy = if placement.flip_y { -1.0 } else { 1.0 };
+        // Undo the rotation around the center, then the flips and the scale
+        let (a, b) = (fx * cos / sx, fx * sin / sx);
+        let (c, d) = (-fy * sin / sy, fy * cos / sy);
+        let (dx, dy) = (self.offset_x as f64 - cx, self.offset_y as f64 - cy);
+        let matrix = [a, b, c, d, a * dx + b * dy + w / 2.0, c * dx + d * dy + h / 2.0];
+        self.mapping = Some(Mapping { matrix, edge, filter: placement.filter.min(3) });
+        self
+    }
+
+    // Secondary pixel for a primary position, tiled with wrap-around after applying the offset
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let Some(mapping) = &self.mapping else {
+            let sec_x = (x as i64 + self.offset_x as i64).rem_euclid(self.width as i64) as usize;
+            let sec_y = (y as i64 + self.offset_y as i64).rem_euclid(self.height as i64) as usize;
+            let idx = (sec_y * self.width as usize + sec_x) * 4;
+            return self.data[idx..idx + 4].try_into().unwrap();
+        };
+        let [a, b, c, d, e, f] = mapping.matrix;
+        let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+        let (u, v) = (a * px + b * py + e, c * px + d * py + f);
+        transform::sample(self.data, self.width as usize, self.height as usize, u, v, mapping.filter, mapping.edge)
+    }
+}
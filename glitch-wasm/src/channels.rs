@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use crate::yuv;
+use crate::{BinaryXorOptions, ByteCorruptOptions, ChannelShiftOptions, DataBendOptions, PixelSortOptions};
+
+// Effects that can run on a single channel, mirroring `ChannelEffects` in useGlitchEffect.ts
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ChannelEffects {
+    pub(crate) pixel_sort: Option<PixelSortOptions>,
+    pub(crate) data_bend: Option<DataBendOptions>,
+    pub(crate) shift: Option<ChannelShiftOptions>,
+    pub(crate) noise: Option<f64>,
+    pub(crate) invert: Option<bool>,
+    pub(crate) quantize: Option<usize>,
+    pub(crate) byte_corrupt: Option<ByteCorruptOptions>,
+    pub(crate) binary_xor: Option<BinaryXorOptions>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChannelChain {
+    pub(crate) space: usize,   // 0=RGBA, 1=HSL, 2=YCbCr
+    pub(crate) channel: usize, // Index within the space (RGBA: 0-3, HSL: 0-2, YCbCr: 0-2)
+    pub(crate) effects: ChannelEffects,
+}
+
+// Grayscale RGBA view of a single plane so any effect can run on it
+pub fn plane_view(plane: &[u8]) -> Vec<u8> {
+    plane.iter().flat_map(|&value| [value, value, value, 255]).collect()
+}
+
+// Read a plane back from a view, averaging RGB so single-channel effects still count
+pub fn plane_from_view(view: &[u8], plane: &mut [u8]) {
+    for (value, pixel) in plane.iter_mut().zip(view.chunks_exact(4)) {
+        *value = ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16 + 1) / 3) as u8;
+    }
+}
+
+// RGB to HSL with every component scaled to 0-255 (hue wraps at 256)
+pub fn rgb_to_hsl(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return [0, 0, (lightness * 255.0).round() as u8];
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    // Hue sector 0-6 around the color wheel
+    let sector = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    let hue = sector / 6.0;
+
+    [
+        ((hue * 256.0).round() as u32 % 256) as u8,
+        (saturation.clamp(0.0, 1.0) * 255.0).round() as u8,
+        (lightness * 255.0).round() as u8,
+    ]
+}
+
+pub fn hsl_to_rgb(h: u8, s: u8, l: u8) -> [u8; 3] {
+    let hue = h as f64 / 256.0 * 6.0;
+    let saturation = s as f64 / 255.0;
+    let lightness = l as f64 / 255.0;
+
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let to_byte = |value: f64| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    [to_byte(r), to_byte(g), to_byte(b)]
+}
+
+// Split RGBA data into the planes of a color space. The fourth plane is always alpha.
+pub fn split(data: &[u8], space: usize) -> [Vec<u8>; 4] {
+    let mut planes = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+    for pixel in data.chunks_exact(4) {
+        let converted = match space {
+            1 => rgb_to_hsl(pixel[0], pixel[1], pixel[2]),
+            2 => yuv::rgb_to_ycbcr(pixel[0], pixel[1], pixel[2]),
+            _ => [pixel[0], pixel[1], pixel[2]],
+        };
+        for (plane, value) in planes.iter_mut().zip(converted.into_iter().chain([pixel[3]])) {
+            plane.push(value);
+        }
+    }
+    planes
+}
+
+// Recombine planes produced by `split` back into RGBA data
+pub fn merge(data: &mut [u8], planes: &[Vec<u8>; 4], space: usize) {
+    for (i, pixel) in data.chunks_exact_mut(4).enumerate() {
+        let (a, b, c) = (planes[0][i], planes[1][i], planes[2][i]);
+        let rgb = match space {
+            1 => hsl_to_rgb(a, b, c),
+            2 => yuv::ycbcr_to_rgb(a, b, c),
+            _ => [a, b, c],
+        };
+        pixel[..3].copy_from_slice(&rgb);
+        pixel[3] = planes[3][i];
+    }
+}
@@ -0,0 +1,501 @@
+// Interactive evolution: a population of recipes where the user picks favorites and the
+// next generation is bred from them by crossover and mutation. Every individual records
+// how it was made (its origin), and since crossover and mutation are seeded, the chain of
+// origins from the first generation re-derives any recipe exactly.
+
+use std::collections::HashMap;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::bitplane::BitPlaneOptions;
+use crate::fourier::FourierOptions;
+use crate::job::{self, Stage};
+use crate::modulation::hash64;
+use crate::randomize::{self, MAX_SEED};
+use crate::recipe::{self, Recipe};
+use crate::sonify::SonifyOptions;
+use crate::yuv::YuvOptions;
+use crate::{
+    BinaryXorOptions, ByteCorruptOptions, ChannelShiftOptions, ChunkSwapOptions, DataBendOptions,
+    GlitchOptions, ImageBlendOptions, PixelSortOptions,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Origin {
+    Random { seed: u64, chaos: f64 },                          // `random_recipe(seed, chaos)`
+    Imported { recipe: Value },                                // A recipe the population started from
+    Bred { parents: [usize; 2], seed: u64, mutation: f64 },    // Crossover of two parents, then mutation
+}
+
+// One step of a lineage: which individual, in which generation, made how
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ancestor {
+    pub(crate) id: usize,
+    pub(crate) generation: usize,
+    pub(crate) origin: Origin,
+}
+
+// Build the recipe an origin describes. Parents are looked up by id, so they must have
+// been derived first; ids always increase from parents to children.
+fn derive<'a>(ancestor: &Ancestor, parent: impl Fn(usize) -> Option<&'a Recipe>) -> Result<Recipe, String> {
+    match &ancestor.origin {
+        Origin::Random { seed, chaos } => Ok(randomize::generate_recipe(*seed, *chaos)),
+        Origin::Imported { recipe } => recipe::from_value(recipe.clone()),
+        Origin::Bred { parents, seed, mutation } => {
+            let [a, b] = parents.map(|id| parent(id).ok_or(format!("Unknown parent {id}")));
+            let (a, b) = (a?, b?);
+            // Crossover with itself would only lose stages, so a single parent is just mutated
+            let mut child = if parents[0] == parents[1] {
+                a.clone()
+            } else {
+                crossover(a.clone(), b.clone(), *seed)
+            };
+            randomize::mutate(&mut child, *mutation, hash64(*seed));
+            child.name = format!("Generation {} #{}", ancestor.generation, ancestor.id);
+            Ok(child)
+        }
+    }
+}
+
+// Seeded source for the crossover decisions
+struct Crossover {
+    rng: SmallRng,
+}
+
+impl Crossover {
+    // Discrete settings (modes, flags, channel lists) come from one parent or the other
+    fn inherit<T>(&mut self, a: T, b: T) -> T {
+        if self.rng.gen_bool(0.5) { a } else { b }
+    }
+
+    // Numeric settings land anywhere between the parents' values
+    fn blend(&mut self, a: f64, b: f64) -> f64 {
+        a + (b - a) * self.rng.gen::<f64>()
+    }
+
+    fn blend_opt(&mut self, a: Option<f64>, b: Option<f64>) -> Option<f64> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(self.blend(a, b)),
+            (a, b) => self.inherit(a, b),
+        }
+    }
+
+    fn blend_int(&mut self, a: Option<i64>, b: Option<i64>) -> Option<i64> {
+        self.blend_opt(a.map(|v| v as f64), b.map(|v| v as f64)).map(|v| v.round() as i64)
+    }
+
+    // Cross an effect both parents have; otherwise keep whichever one exists
+    fn combine<T>(&mut self, a: Option<T>, b: Option<T>, cross: impl FnOnce(&mut Self, T, T) -> T) -> Option<T> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(cross(self, a, b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // One-point crossover of two lists: the head of `a` followed by the tail of `b`
+    fn splice<T>(&mut self, mut a: Vec<T>, mut b: Vec<T>) -> Vec<T> {
+        let cut_a = self.rng.gen_range(0..=a.len());
+        let cut_b = self.rng.gen_range(0..=b.len());
+        a.truncate(cut_a);
+        a.extend(b.drain(cut_b..));
+        a
+    }
+}
+
+// Breed two recipes. The pipeline is spliced (the first steps of one parent followed by the
+// last steps of the other); an effect both parents have gets blended numeric parameters and
+// modes inherited from either parent.
+pub fn crossover(a: Recipe, b: Recipe, seed: u64) -> Recipe {
+    let mut c = Crossover { rng: SmallRng::seed_from_u64(seed) };
+    let (mut ea, mut eb) = (a.effects, b.effects);
+
+    let stages_a = job::stages(&ea);
+    let stages_b = job::stages(&eb);
+    let mut order: Vec<Stage> = Vec::new();
+    for stage in c.splice(stages_a.clone(), stages_b.clone()) {
+        if !order.contains(&stage) {
+            order.push(stage);
+        }
+    }
+    if order.is_empty() {
+        order = if stages_a.is_empty() { stages_b } else { stages_a };
+    }
+
+    let mut effects = GlitchOptions::default();
+    for &stage in &order {
+        cross_stage(&mut effects, stage, &mut ea, &mut eb, &mut c);
+    }
+    // Only spell out the order when it differs from the pipeline's own
+    if job::stages(&effects) != order {
+        effects.order = Some(order.iter().map(|stage| stage.name().to_string()).collect());
+    }
+
+    // Animation and audio settings belong to the whole pipeline, so they come from one parent
+    let (modulation, audio, audio_mapping, absolute_units, masks) = c.inherit(
+        (ea.modulation, ea.audio, ea.audio_mapping, ea.absolute_units, ea.masks),
+        (eb.modulation, eb.audio, eb.audio_mapping, eb.absolute_units, eb.masks),
+    );
+    effects.modulation = modulation;
+    effects.audio = audio;
+    effects.audio_mapping = audio_mapping;
+    effects.absolute_units = absolute_units;
+    effects.masks = masks;
+
+    // Either parent's blend image may have been inherited, so keep both parents' assets
+    let mut assets = a.assets.unwrap_or_default();
+    for asset in b.assets.unwrap_or_default() {
+        if asset.hash.is_none() || !assets.iter().any(|kept| kept.hash == asset.hash) {
+            assets.push(asset);
+        }
+    }
+
+    let seed = c.inherit(a.seed.or(ea.seed), b.seed.or(eb.seed)).unwrap_or(seed);
+    let mut child = Recipe::new(format!("{} × {}", a.name, b.name), seed, effects);
+    child.tags = c.inherit(a.tags, b.tags);
+    child.timeline = c.inherit(a.timeline, b.timeline);
+    child.shader = c.inherit(a.shader, b.shader);
+    child.assets = (!assets.is_empty()).then_some(assets);
+    child
+}
+
+fn cross_stage(child: &mut GlitchOptions, stage: Stage, a: &mut GlitchOptions, b: &mut GlitchOptions, c: &mut Crossover) {
+    match stage {
+        Stage::PixelSort => child.pixel_sort = c.combine(a.pixel_sort.take(), b.pixel_sort.take(), cross_pixel_sort),
+        Stage::DataBend => child.data_bend = c.combine(a.data_bend.take(), b.data_bend.take(), cross_data_bend),
+        Stage::ChannelShift => {
+            child.channel_shift = c.combine(a.channel_shift.take(), b.channel_shift.take(), cross_channel_shift)
+        }
+        Stage::Noise => child.noise = c.combine(a.noise, b.noise, Crossover::blend),
+        Stage::Invert => child.invert = c.combine(a.invert.take(), b.invert.take(), Crossover::inherit),
+        Stage::Quantize => {
+            child.quantize = c.combine(a.quantize, b.quantize, |c, a, b| {
+                c.blend(a as f64, b as f64).round() as usize
+            })
+        }
+        Stage::ByteCorrupt => {
+            child.byte_corrupt = c.combine(a.byte_corrupt.take(), b.byte_corrupt.take(), cross_byte_corrupt)
+        }
+        Stage::ChunkSwap => child.chunk_swap = c.combine(a.chunk_swap.take(), b.chunk_swap.take(), cross_chunk_swap),
+        Stage::BinaryXor => child.binary_xor = c.combine(a.binary_xor.take(), b.binary_xor.take(), cross_binary_xor),
+        Stage::ImageBlend => {
+            child.image_blend = c.combine(a.image_blend.take(), b.image_blend.take(), cross_image_blend)
+        }
+        Stage::Sonify => child.sonify = c.combine(a.sonify.take(), b.sonify.take(), cross_sonify),
+        Stage::Fourier => child.fourier = c.combine(a.fourier.take(), b.fourier.take(), cross_fourier),
+        Stage::BitPlane => child.bit_plane = c.combine(a.bit_plane.take(), b.bit_plane.take(), cross_bit_plane),
+        Stage::Reinterpret => {
+            child.reinterpret = c.combine(a.reinterpret.take(), b.reinterpret.take(), Crossover::inherit)
+        }
+        Stage::Yuv => child.yuv = c.combine(a.yuv.take(), b.yuv.take(), cross_yuv),
+        Stage::ChannelChains => {
+            child.red_channel = c.combine(a.red_channel.take(), b.red_channel.take(), Crossover::inherit);
+            child.green_channel = c.combine(a.green_channel.take(), b.green_channel.take(), Crossover::inherit);
+            child.blue_channel = c.combine(a.blue_channel.take(), b.blue_channel.take(), Crossover::inherit);
+            child.alpha_channel = c.combine(a.alpha_channel.take(), b.alpha_channel.take(), Crossover::inherit);
+            child.channel_chains = c.combine(a.channel_chains.take(), b.channel_chains.take(), Crossover::splice);
+        }
+        Stage::Layers => child.layers = c.combine(a.layers.take(), b.layers.take(), Crossover::splice),
+        Stage::Transform => child.transform = c.combine(a.transform.take(), b.transform.take(), Crossover::inherit),
+    }
+}
+
+fn cross_pixel_sort(c: &mut Crossover, a: PixelSortOptions, b: PixelSortOptions) -> PixelSortOptions {
+    let intensity = c.blend(a.intensity, b.intensity);
+    let threshold = c.blend(a.threshold, b.threshold);
+    let auto_target = c.blend_opt(a.auto_target, b.auto_target);
+    PixelSortOptions { intensity, threshold, auto_target, ..c.inherit(a, b) }
+}
+
+fn cross_data_bend(c: &mut Crossover, a: DataBendOptions, b: DataBendOptions) -> DataBendOptions {
+    let amount = c.blend(a.amount, b.amount);
+    let chunk_size = c.blend_opt(a.chunk_size, b.chunk_size);
+    DataBendOptions { amount, chunk_size, ..c.inherit(a, b) }
+}
+
+fn cross_channel_shift(c: &mut Crossover, a: ChannelShiftOptions, b: ChannelShiftOptions) -> ChannelShiftOptions {
+    let amount = c.blend(a.amount, b.amount);
+    ChannelShiftOptions { amount, ..c.inherit(a, b) }
+}
+
+fn cross_byte_corrupt(c: &mut Crossover, a: ByteCorruptOptions, b: ByteCorruptOptions) -> ByteCorruptOptions {
+    let amount = c.blend(a.amount, b.amount);
+    let block_size = c.blend_int(a.block_size.map(|v| v as i64), b.block_size.map(|v| v as i64));
+    ByteCorruptOptions { amount, block_size: block_size.map(|v| v.max(1) as usize), ..c.inherit(a, b) }
+}
+
+fn cross_chunk_swap(c: &mut Crossover, a: ChunkSwapOptions, b: ChunkSwapOptions) -> ChunkSwapOptions {
+    let amount = c.blend(a.amount, b.amount);
+    let chunk_size = c.blend_opt(a.chunk_size, b.chunk_size);
+    ChunkSwapOptions { amount, chunk_size, ..c.inherit(a, b) }
+}
+
+fn cross_binary_xor(c: &mut Crossover, a: BinaryXorOptions, b: BinaryXorOptions) -> BinaryXorOptions {
+    let strength = c.blend(a.strength, b.strength);
+    BinaryXorOptions { strength, ..c.inherit(a, b) }
+}
+
+// The secondary image and blend mode travel together
+fn cross_image_blend(c: &mut Crossover, a: ImageBlendOptions, b: ImageBlendOptions) -> ImageBlendOptions {
+    let amount = c.blend(a.amount, b.amount);
+    let offset_x = c.blend(a.offset_x as f64, b.offset_x as f64).round() as i32;
+    let offset_y = c.blend(a.offset_y as f64, b.offset_y as f64).round() as i32;
+    ImageBlendOptions { amount, offset_x, offset_y, ..c.inherit(a, b) }
+}
+
+// Effect-specific parameters only blend when both parents run the same effect
+fn cross_sonify(c: &mut Crossover, a: SonifyOptions, b: SonifyOptions) -> SonifyOptions {
+    let amount = c.blend(a.amount, b.amount);
+    if a.effect != b.effect {
+        return SonifyOptions { amount, ..c.inherit(a, b) };
+    }
+    let delay = c.blend_opt(a.delay, b.delay);
+    let feedback = c.blend_opt(a.feedback, b.feedback);
+    let cutoff = c.blend_opt(a.cutoff, b.cutoff);
+    let resonance = c.blend_opt(a.resonance, b.resonance);
+    let bits = c.blend_int(a.bits.map(i64::from), b.bits.map(i64::from)).map(|v| v.clamp(1, 16) as u32);
+    let downsample = c.blend_int(a.downsample.map(|v| v as i64), b.downsample.map(|v| v as i64));
+    let drive = c.blend_opt(a.drive, b.drive);
+    let rate = c.blend_opt(a.rate, b.rate);
+    let stretch = c.blend_opt(a.stretch, b.stretch);
+    SonifyOptions {
+        amount,
+        delay,
+        feedback,
+        cutoff,
+        resonance,
+        bits,
+        downsample: downsample.map(|v| v.max(1) as usize),
+        drive,
+        rate,
+        stretch,
+        ..c.inherit(a, b)
+    }
+}
+
+fn cross_fourier(c: &mut Crossover, a: FourierOptions, b: FourierOptions) -> FourierOptions {
+    let amount = c.blend(a.amount, b.amount);
+    if a.mode != b.mode {
+        return FourierOptions { amount, ..c.inherit(a, b) };
+    }
+    let mut low = c.blend_opt(a.low, b.low);
+    let mut high = c.blend_opt(a.high, b.high);
+    if let (Some(l), Some(h)) = (low, high) {
+        if l > h {
+            (low, high) = (Some(h), Some(l));
+        }
+    }
+    let angle = c.blend_opt(a.angle, b.angle);
+    let levels = c.blend_int(a.levels.map(|v| v as i64), b.levels.map(|v| v as i64));
+    FourierOptions { amount, low, high, angle, levels: levels.map(|v| v.max(2) as usize), ..c.inherit(a, b) }
+}
+
+fn cross_bit_plane(c: &mut Crossover, a: BitPlaneOptions, b: BitPlaneOptions) -> BitPlaneOptions {
+    if a.operation != b.operation {
+        return c.inherit(a, b);
+    }
+    let shift = c.blend_int(a.shift.map(i64::from), b.shift.map(i64::from));
+    BitPlaneOptions { shift: shift.map(|v| v as i32), ..c.inherit(a, b) }
+}
+
+fn cross_yuv(c: &mut Crossover, mut a: YuvOptions, mut b: YuvOptions) -> YuvOptions {
+    let chroma_shift_x = c.blend_int(a.chroma_shift_x.map(i64::from), b.chroma_shift_x.map(i64::from));
+    let chroma_shift_y = c.blend_int(a.chroma_shift_y.map(i64::from), b.chroma_shift_y.map(i64::from));
+    let luma_sort = c.combine(a.luma_sort.take(), b.luma_sort.take(), cross_pixel_sort);
+    YuvOptions {
+        chroma_shift_x: chroma_shift_x.map(|v| v as i32),
+        chroma_shift_y: chroma_shift_y.map(|v| v as i32),
+        luma_sort,
+        ..c.inherit(a, b)
+    }
+}
+
+struct Individual {
+    ancestor: Ancestor,
+    recipe: Recipe,
+}
+
+// A breeding session. Holds every individual ever created (ids index into it) so favorites
+// can come from any generation and lineages stay complete.
+#[wasm_bindgen]
+pub struct Population {
+    individuals: Vec<Individual>,
+    current: Vec<usize>, // Ids in the current generation
+    generation: usize,
+    size: usize,
+    seed: u64,
+    chaos: f64,    // For random individuals
+    mutation: f64, // Mutation amount for bred individuals
+}
+
+impl Population {
+    fn empty(size: usize, seed: u64, chaos: f64, mutation: f64) -> Self {
+        Self {
+            individuals: Vec::new(),
+            current: Vec::new(),
+            generation: 0,
+            size: size.max(1),
+            seed,
+            chaos,
+            mutation,
+        }
+    }
+
+    fn add(&mut self, origin: Origin) -> Result<usize, String> {
+        let id = self.individuals.len();
+        let ancestor = Ancestor { id, generation: self.generation, origin };
+        let recipe = derive(&ancestor, |parent| self.individuals.get(parent).map(|i| &i.recipe))?;
+        self.individuals.push(Individual { ancestor, recipe });
+        self.current.push(id);
+        Ok(id)
+    }
+
+    // Seed for the individual that will get `id`, kept within JS's exact integer range
+    fn seed_for(&self, id: usize) -> u64 {
+        hash64(self.seed ^ hash64(id as u64)) & MAX_SEED
+    }
+
+    fn fill_random(&mut self) -> Result<(), String> {
+        while self.current.len() < self.size {
+            let seed = self.seed_for(self.individuals.len());
+            self.add(Origin::Random { seed, chaos: self.chaos })?;
+        }
+        Ok(())
+    }
+
+    fn individual(&self, id: usize) -> Result<&Individual, JsValue> {
+        self.individuals.get(id).ok_or_else(|| JsValue::from_str("Unknown individual"))
+    }
+
+    pub(crate) fn recipe_internal(&self, id: usize) -> Result<&Recipe, JsValue> {
+        Ok(&self.individual(id)?.recipe)
+    }
+}
+
+#[wasm_bindgen]
+impl Population {
+    // First generation of `size` random recipes
+    #[wasm_bindgen(constructor)]
+    pub fn new(size: usize, seed: u64, chaos: f64) -> Result<Population, JsValue> {
+        let mut population = Self::empty(size, seed, chaos, 0.3);
+        population.fill_random()?;
+        Ok(population)
+    }
+
+    // First generation made of a recipe (any version) and mutations of it
+    pub fn from_recipe(recipe_js: JsValue, size: usize, seed: u64, mutation: f64) -> Result<Population, JsValue> {
+        let base = recipe::to_value(&recipe::from_js(recipe_js)?)?;
+        let mut population = Self::empty(size, seed, 0.5, mutation);
+        let base = population.add(Origin::Imported { recipe: base })?;
+        while population.current.len() < population.size {
+            let seed = population.seed_for(population.individuals.len());
+            population.add(Origin::Bred { parents: [base, base], seed, mutation })?;
+        }
+        Ok(population)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    // Ids of the current generation, for rendering thumbnails and picking favorites
+    pub fn ids(&self) -> Vec<usize> {
+        self.current.clone()
+    }
+
+    // How far children move from their parents (0.0-1.0, default: 0.3)
+    pub fn set_mutation(&mut self, amount: f64) {
+        self.mutation = amount.clamp(0.0, 1.0);
+    }
+
+    // Chaos level for random individuals (0.0-1.0)
+    pub fn set_chaos(&mut self, chaos: f64) {
+        self.chaos = chaos.clamp(0.0, 1.0);
+    }
+
+    pub fn recipe(&self, id: usize) -> Result<JsValue, JsValue> {
+        recipe::to_js(self.recipe_internal(id)?)
+    }
+
+    // Breed the next generation from favorite ids (from any generation). Favorites carry over
+    // unchanged and the rest are children of random pairs of them; with no favorites the
+    // next generation is random. Returns the new generation's ids.
+    pub fn next_generation(&mut self, favorites: Vec<usize>) -> Result<Vec<usize>, JsValue> {
+        if favorites.iter().any(|&id| id >= self.individuals.len()) {
+            return Err(JsValue::from_str("Unknown individual"));
+        }
+        self.generation += 1;
+        self.current.clear();
+
+        if favorites.is_empty() {
+            self.fill_random()?;
+            return Ok(self.ids());
+        }
+
+        // Drop repeated ids, keeping the first of each, so the two parents always differ
+        let mut favorites: Vec<usize> = favorites.iter().enumerate()
+            .filter(|&(i, id)| !favorites[..i].contains(id))
+            .map(|(_, &id)| id)
+            .collect();
+        favorites.truncate(self.size);
+        self.current.extend(&favorites);
+
+        let mut rng = SmallRng::seed_from_u64(hash64(self.seed ^ self.generation as u64));
+        while self.current.len() < self.size {
+            let index = rng.gen_range(0..favorites.len());
+            let a = favorites[index];
+            let b = if favorites.len() > 1 {
+                // Pick a different second parent
+                let other = rng.gen_range(0..favorites.len() - 1);
+                favorites[if other >= index { other + 1 } else { other }]
+            } else {
+                a
+            };
+            let seed = self.seed_for(self.individuals.len());
+            self.add(Origin::Bred { parents: [a, b], seed, mutation: self.mutation })?;
+        }
+        Ok(self.ids())
+    }
+
+    // Everything needed to re-derive an individual: its ancestors and itself, oldest first.
+    // Pass it to `rederive_recipe` to rebuild the recipe without the population.
+    pub fn lineage(&self, id: usize) -> Result<JsValue, JsValue> {
+        let mut ids = vec![id];
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            if let Origin::Bred { parents, .. } = &self.individual(id)?.ancestor.origin {
+                for parent in parents {
+                    if !ids.contains(parent) {
+                        ids.push(*parent);
+                        pending.push(*parent);
+                    }
+                }
+            }
+        }
+        ids.sort_unstable();
+        let lineage: Vec<&Ancestor> = ids.iter().map(|&id| &self.individuals[id].ancestor).collect();
+        Ok(lineage.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+    }
+}
+
+// Rebuild the last individual of a lineage from `Population::lineage`
+#[wasm_bindgen]
+pub fn rederive_recipe(lineage_js: JsValue) -> Result<JsValue, JsValue> {
+    let lineage: Vec<Ancestor> = serde_wasm_bindgen::from_value(lineage_js)?;
+    let mut recipes: HashMap<usize, Recipe> = HashMap::new();
+    let mut last = None;
+    for ancestor in &lineage {
+        let recipe = derive(ancestor, |id| recipes.get(&id))?;
+        recipes.insert(ancestor.id, recipe);
+        last = Some(ancestor.id);
+    }
+    match last.and_then(|id| recipes.get(&id)) {
+        Some(recipe) => recipe::to_js(recipe),
+        None => Err(JsValue::from_str("Empty lineage")),
+    }
+}
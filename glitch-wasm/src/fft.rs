@@ -0,0 +1,188 @@
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn from_polar(magnitude: f64, phase: f64) -> Self {
+        Self::new(magnitude * phase.cos(), magnitude * phase.sin())
+    }
+
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn scale(self, factor: f64) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+// In-place iterative radix-2 FFT. The length must be a power of two; the inverse
+// transform is scaled by 1/n so a forward + inverse round trip is lossless.
+pub fn fft(buffer: &mut [Complex], inverse: bool) {
+    let n = buffer.len();
+    if n <= 1 { return; }
+    debug_assert!(n.is_power_of_two(), "FFT length must be a power of two");
+
+    // Bit-reversal permutation
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            buffer.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut size = 2;
+    while size <= n {
+        let angle = sign * std::f64::consts::TAU / size as f64;
+        let twiddles: Vec<Complex> = (0..size / 2)
+            .map(|k| Complex::from_polar(1.0, angle * k as f64))
+            .collect();
+        for start in (0..n).step_by(size) {
+            for (k, &twiddle) in twiddles.iter().enumerate() {
+                let even = buffer[start + k];
+                let odd = buffer[start + k + size / 2] * twiddle;
+                buffer[start + k] = even + odd;
+                buffer[start + k + size / 2] = even - odd;
+            }
+        }
+        size *= 2;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        for value in buffer.iter_mut() {
+            *value = value.scale(scale);
+        }
+    }
+}
+
+// FFT of any length. Power-of-two sizes use the radix-2 transform directly, other
+// sizes go through Bluestein's chirp-z algorithm with a precomputed kernel.
+pub struct FftPlan {
+    len: usize,
+    padded: usize,
+    chirp: Vec<Complex>,
+    kernel: Vec<Complex>,
+}
+
+impl FftPlan {
+    pub fn new(len: usize) -> Self {
+        if len.is_power_of_two() || len <= 1 {
+            return Self { len, padded: len, chirp: Vec::new(), kernel: Vec::new() };
+        }
+
+        let padded = (2 * len - 1).next_power_of_two();
+        // w_k = exp(-i * pi * k^2 / n), with k^2 reduced mod 2n to keep the angle precise
+        let chirp: Vec<Complex> = (0..len)
+            .map(|k| {
+                let k2 = (k as u128 * k as u128 % (2 * len as u128)) as f64;
+                Complex::from_polar(1.0, -std::f64::consts::PI * k2 / len as f64)
+            })
+            .collect();
+
+        let mut kernel = vec![Complex::default(); padded];
+        for k in 0..len {
+            let conj = Complex::new(chirp[k].re, -chirp[k].im);
+            kernel[k] = conj;
+            if k > 0 {
+                kernel[padded - k] = conj;
+            }
+        }
+        fft(&mut kernel, false);
+
+        Self { len, padded, chirp, kernel }
+    }
+
+    pub fn process(&self, buffer: &mut [Complex], inverse: bool) {
+        debug_assert_eq!(buffer.len(), self.len);
+        if self.chirp.is_empty() {
+            fft(buffer, inverse);
+            return;
+        }
+
+        // The inverse is the conjugate of the forward transform of the conjugate, scaled by 1/n
+        if inverse {
+            for value in buffer.iter_mut() {
+                value.im = -value.im;
+            }
+        }
+
+        let mut work = vec![Complex::default(); self.padded];
+        for k in 0..self.len {
+            work[k] = buffer[k] * self.chirp[k];
+        }
+        fft(&mut work, false);
+        for (value, &kernel) in work.iter_mut().zip(&self.kernel) {
+            *value = *value * kernel;
+        }
+        fft(&mut work, true);
+        for k in 0..self.len {
+            buffer[k] = work[k] * self.chirp[k];
+        }
+
+        if inverse {
+            let scale = 1.0 / self.len as f64;
+            for value in buffer.iter_mut() {
+                *value = Complex::new(value.re * scale, -value.im * scale);
+            }
+        }
+    }
+}
+
+// In-place 2D transform of a row-major width x height buffer
+pub fn fft_2d(buffer: &mut [Complex], width: usize, height: usize, inverse: bool) {
+    let row_plan = FftPlan::new(width);
+    for row in buffer.chunks_exact_mut(width) {
+        row_plan.process(row, inverse);
+    }
+
+    let column_plan = FftPlan::new(height);
+    let mut column = vec![Complex::default(); height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = buffer[y * width + x];
+        }
+        column_plan.process(&mut column, inverse);
+        for y in 0..height {
+            buffer[y * width + x] = column[y];
+        }
+    }
+}
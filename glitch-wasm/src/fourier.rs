@@ -0,0 +1,193 @@
+use rand::Rng;
+use rand::rngs::SmallRng;
+use serde::{Deserialize, Serialize};
+
+use crate::fft::{fft_2d, Complex};
+use crate::buffer::{ImageStore, SecondaryImage};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FourierOptions {
+    pub(crate) mode: usize,           // 0=phase scramble, 1=magnitude swap, 2=phase swap, 3=band-pass, 4=notch, 5=spectral rotation, 6=magnitude quantize
+    pub(crate) amount: f64,           // Effect intensity (0.0-1.0)
+    pub(crate) channel: Option<usize>, // 0=R, 1=G, 2=B, None=RGB
+    pub(crate) low: Option<f64>,      // Band lower radius relative to Nyquist (default: 0.1)
+    pub(crate) high: Option<f64>,     // Band upper radius relative to Nyquist (default: 0.5)
+    pub(crate) angle: Option<f64>,    // Spectral rotation in degrees (default: 45)
+    pub(crate) levels: Option<usize>, // Magnitude quantization levels (default: 4)
+    pub(crate) secondary_image: Option<u32>,     // Handle from `register_image` whose spectrum the swaps take
+    pub(crate) secondary_asset: Option<String>,  // Content hash of a registered image, used when there's no handle
+}
+
+impl FourierOptions {
+    // Resolve the swap image, preferring a registered handle over a content hash. It is tiled
+    // at its native size, like an image passed to `fourier` directly.
+    pub(crate) fn secondary<'a>(&self, images: &'a ImageStore) -> Option<SecondaryImage<'a>> {
+        match (self.secondary_image, &self.secondary_asset) {
+            (Some(handle), _) => images.get(handle, 0, 0),
+            (None, Some(hash)) => images.find(hash, 0, 0),
+            (None, None) => None,
+        }
+    }
+}
+
+// Signed frequency of index `i` in a transform of length `n`, relative to Nyquist (-1.0..1.0)
+fn frequency(i: usize, n: usize) -> f64 {
+    let signed = if i <= n / 2 { i as f64 } else { i as f64 - n as f64 };
+    signed / (n as f64 / 2.0).max(1.0)
+}
+
+// Blend two spectra by magnitude and phase separately so phases interpolate along the short arc
+fn lerp_polar(a: Complex, b: Complex, t: f64) -> Complex {
+    let magnitude = a.norm() + (b.norm() - a.norm()) * t;
+    let mut delta = b.arg() - a.arg();
+    if delta > std::f64::consts::PI { delta -= std::f64::consts::TAU; }
+    if delta < -std::f64::consts::PI { delta += std::f64::consts::TAU; }
+    Complex::from_polar(magnitude, a.arg() + delta * t)
+}
+
+fn lerp(a: Complex, b: Complex, t: f64) -> Complex {
+    a + (b - a).scale(t)
+}
+
+fn channel_spectrum(data: &[u8], channel: usize, width: usize, height: usize) -> Vec<Complex> {
+    let mut spectrum: Vec<Complex> = data.chunks_exact(4)
+        .map(|pixel| Complex::new(pixel[channel] as f64, 0.0))
+        .collect();
+    fft_2d(&mut spectrum, width, height, false);
+    spectrum
+}
+
+// Secondary image sampled over the primary's dimensions
+fn secondary_buffer(secondary: &SecondaryImage, width: usize, height: usize) -> Vec<u8> {
+    let mut buffer = vec![0; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = secondary.pixel(x, y);
+            buffer[(y * width + x) * 4..(y * width + x) * 4 + 4].copy_from_slice(&pixel);
+        }
+    }
+    buffer
+}
+
+pub fn fourier(data: &mut [u8], width: u32, options: &FourierOptions,
+               secondary: Option<&SecondaryImage>, scale: f64, rng: &mut SmallRng) {
+    let width = width as usize;
+    if width == 0 { return; }
+    let height = data.len() / 4 / width;
+    if height == 0 { return; }
+    let len = width * height;
+    let amount = options.amount.clamp(0.0, 1.0);
+
+    let channels = match options.channel {
+        Some(ch) if ch < 3 => vec![ch],
+        _ => vec![0, 1, 2],
+    };
+
+    // Swaps need a secondary image; without one there is nothing to do
+    let secondary = match options.mode {
+        1 | 2 => match secondary.map(|s| secondary_buffer(s, width, height)) {
+            Some(buffer) => Some(buffer),
+            None => return,
+        },
+        _ => None,
+    };
+
+    // Conjugate-symmetric random phases so the inverse transform stays real
+    let scramble: Vec<f64> = if options.mode == 0 {
+        let mut phases = vec![0.0; len];
+        for v in 0..height {
+            for u in 0..width {
+                let i = v * width + u;
+                let mirror = ((height - v) % height) * width + (width - u) % width;
+                if i < mirror {
+                    let phase = rng.gen_range(-std::f64::consts::PI..std::f64::consts::PI);
+                    phases[i] = phase;
+                    phases[mirror] = -phase;
+                }
+            }
+        }
+        phases
+    } else {
+        Vec::new()
+    };
+
+    // Band radii follow the image size so the same features are kept at any resolution
+    let scale = scale.max(f64::EPSILON);
+    let low = options.low.unwrap_or(0.1) / scale;
+    let high = options.high.unwrap_or(0.5) / scale;
+    let angle = options.angle.unwrap_or(45.0).to_radians();
+    let levels = options.levels.unwrap_or(4).max(2);
+
+    for &ch in &channels {
+        let spectrum = channel_spectrum(data, ch, width, height);
+        let other = secondary.as_ref().map(|s| channel_spectrum(s, ch, width, height));
+        let mut result = spectrum.clone();
+
+        match options.mode {
+            0 => {
+                for (i, value) in result.iter_mut().enumerate() {
+                    *value = Complex::from_polar(value.norm(), value.arg() + scramble[i] * amount);
+                }
+            }
+            1 | 2 => {
+                let other = other.as_ref().unwrap();
+                for (i, value) in result.iter_mut().enumerate() {
+                    let swapped = if options.mode == 1 {
+                        Complex::from_polar(other[i].norm(), spectrum[i].arg())
+                    } else {
+                        Complex::from_polar(spectrum[i].norm(), other[i].arg())
+                    };
+                    *value = lerp_polar(spectrum[i], swapped, amount);
+                }
+            }
+            3 | 4 => {
+                for v in 0..height {
+                    for u in 0..width {
+                        if u == 0 && v == 0 { continue; } // Keep the mean brightness
+                        let radius = frequency(u, width).hypot(frequency(v, height));
+                        let inside = radius >= low && radius <= high;
+                        let keep = if options.mode == 3 { inside } else { !inside };
+                        if !keep {
+                            let i = v * width + u;
+                            result[i] = result[i].scale(1.0 - amount);
+                        }
+                    }
+                }
+            }
+            5 => {
+                let (sin, cos) = angle.sin_cos();
+                for v in 0..height {
+                    for u in 0..width {
+                        // Inverse-rotate the signed frequency and sample the nearest source bin
+                        let fu = frequency(u, width) * width as f64 / 2.0;
+                        let fv = frequency(v, height) * height as f64 / 2.0;
+                        let su = (fu * cos + fv * sin).round() as i64;
+                        let sv = (-fu * sin + fv * cos).round() as i64;
+                        let su = su.rem_euclid(width as i64) as usize;
+                        let sv = sv.rem_euclid(height as i64) as usize;
+                        let i = v * width + u;
+                        result[i] = lerp(spectrum[i], spectrum[sv * width + su], amount);
+                    }
+                }
+            }
+            6 => {
+                // Quantize log magnitudes, keeping the DC term intact
+                let max_log = spectrum.iter().skip(1).map(|c| c.norm().ln_1p()).fold(0.0, f64::max);
+                if max_log > 0.0 {
+                    let step = max_log / (levels - 1) as f64;
+                    for value in result.iter_mut().skip(1) {
+                        let quantized = ((value.norm().ln_1p() / step).round() * step).exp_m1();
+                        let target = Complex::from_polar(quantized, value.arg());
+                        *value = lerp(*value, target, amount);
+                    }
+                }
+            }
+            _ => return,
+        }
+
+        fft_2d(&mut result, width, height, true);
+        for (pixel, value) in data.chunks_exact_mut(4).zip(&result) {
+            pixel[ch] = value.re.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
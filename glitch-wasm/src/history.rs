@@ -0,0 +1,342 @@
+// Editing history with cached intermediate results. The original image stays in wasm memory
+// together with the output of every pipeline step of recent renders, keyed by a hash of the
+// step's options and everything before it. When a late step changes, rendering resumes from
+// the cached result of the step before it. The undo/redo stack holds option sets only, and
+// any entry can be re-rendered from the cache, so JS doesn't need to keep image copies.
+//
+// Caching needs reproducible steps, so renders without a seed always run the whole pipeline.
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+use web_sys::ImageData;
+
+use crate::job::{self, Stage};
+use crate::layers::LayerSource;
+use crate::masks::Maps;
+use crate::{GlitchEffect, GlitchOptions};
+
+const DEFAULT_CACHE_MB: f64 = 128.0;
+
+type Key = [u8; 32];
+
+struct CachedStep {
+    key: Key,
+    data: Vec<u8>,
+    next_seed: u64, // Random stream for the steps after this one
+    used: u64,      // Last use, for least-recently-used eviction
+}
+
+// Undo/redo stack as saved and restored by `snapshot` and `restore`
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    entries: Vec<GlitchOptions>,
+    position: usize,
+}
+
+#[wasm_bindgen]
+pub struct EffectHistory {
+    original: Vec<u8>,
+    width: u32,
+    height: u32,
+    source: Key,               // Hash of the original image, the root of every cache key
+    cache: Vec<CachedStep>,
+    cache_limit: usize,        // Bytes of intermediate results to keep
+    clock: u64,
+    entries: Vec<GlitchOptions>,
+    position: usize,           // Entries applied: the current one is `entries[position - 1]`, 0 is the original
+}
+
+fn cache_bytes(megabytes: f64) -> usize {
+    (megabytes.max(0.0) * 1024.0 * 1024.0) as usize
+}
+
+fn source_key(data: &[u8], width: u32, height: u32) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(width.to_le_bytes());
+    hasher.update(height.to_le_bytes());
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+// Option fields a stage reads besides the shared ones
+fn stage_fields(stage: Stage) -> &'static [&'static str] {
+    match stage {
+        Stage::Fourier => &["fourier"],
+        Stage::ChannelChains => &["red_channel", "green_channel", "blue_channel", "alpha_channel", "channel_chains"],
+        _ => &[],
+    }
+}
+
+// Registered images a stage reads, including those of effects nested in its layers or YUV
+// planes, so cached results notice when they change
+fn stage_images(options: &GlitchOptions, stage: Stage, handles: &mut Vec<u32>) {
+    match stage {
+        Stage::ImageBlend => handles.extend(options.image_blend.as_ref().and_then(|o| o.secondary_image)),
+        Stage::Fourier => handles.extend(options.fourier.as_ref().and_then(|o| o.secondary_image)),
+        Stage::Yuv => {
+            for plane in options.yuv.iter().flat_map(|o| o.planes.iter().flatten()) {
+                image_handles(&plane.effects, handles);
+            }
+        }
+        Stage::Layers => {
+            for layer in options.layers.iter().flatten() {
+                if let LayerSource::Image { image: Some(handle), .. } = layer.source {
+                    handles.push(handle);
+                }
+                handles.extend(layer.mask.as_ref().and_then(|mask| mask.image));
+                if let Some(effects) = &layer.effects {
+                    image_handles(effects, handles);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Registered images any stage of the options reads
+fn image_handles(options: &GlitchOptions, handles: &mut Vec<u32>) {
+    for stage in job::stages(options) {
+        stage_images(options, stage, handles);
+    }
+}
+
+impl EffectHistory {
+    pub(crate) fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub(crate) fn current_options(&self) -> GlitchOptions {
+        match self.position {
+            0 => GlitchOptions::default(),
+            position => self.entries[position - 1].clone(),
+        }
+    }
+
+    fn set_original(&mut self, image_data: &ImageData) {
+        self.original = image_data.data().to_vec();
+        self.width = image_data.width();
+        self.height = image_data.height();
+        self.source = source_key(&self.original, self.width, self.height);
+        self.cache.clear();
+    }
+
+    // Key for a step's output: the key of its input plus everything the step depends on.
+    // The random stream at a step only depends on the seed and the step's position, and the
+    // chain of keys starts from the original image and the seed.
+    fn step_key(&self, effect: &GlitchEffect, previous: &Key, values: &Value, options: &GlitchOptions, stage: Stage) -> Key {
+        let fields = match stage_fields(stage) {
+            [] => vec![stage.name()],
+            fields => fields.to_vec(),
+        };
+        let masks: Vec<&Value> = values["masks"].as_array().into_iter().flatten()
+            .filter(|mask| mask["effect"] == stage.name())
+            .collect();
+        // Registered images are referenced by handle, so use their content instead
+        let mut handles = Vec::new();
+        stage_images(options, stage, &mut handles);
+        let images: Vec<Option<&str>> = handles.into_iter().map(|handle| effect.images.hash(handle)).collect();
+        let step = json!({
+            "stage": stage.name(),
+            "options": fields.iter().map(|field| &values[*field]).collect::<Vec<_>>(),
+            "absolute_units": values["absolute_units"],
+            "masks": masks,
+            "images": images,
+        });
+        let mut hasher = Sha256::new();
+        hasher.update(previous);
+        hasher.update(step.to_string());
+        hasher.finalize().into()
+    }
+
+    fn lookup(&mut self, key: &Key) -> Option<&CachedStep> {
+        self.clock += 1;
+        let clock = self.clock;
+        let step = self.cache.iter_mut().find(|step| &step.key == key)?;
+        step.used = clock;
+        Some(step)
+    }
+
+    fn store(&mut self, key: Key, data: &[u8], next_seed: u64) {
+        if data.len() > self.cache_limit || self.cache.iter().any(|step| step.key == key) {
+            return;
+        }
+        self.clock += 1;
+        self.cache.push(CachedStep { key, data: data.to_vec(), next_seed, used: self.clock });
+        self.evict(self.cache_limit);
+    }
+
+    // Drop the least recently used results until the cache fits in `limit` bytes
+    fn evict(&mut self, limit: usize) {
+        let mut total: usize = self.cache.iter().map(|step| step.data.len()).sum();
+        while total > limit {
+            let oldest = (0..self.cache.len()).min_by_key(|&i| self.cache[i].used).unwrap();
+            total -= self.cache.swap_remove(oldest).data.len();
+        }
+    }
+
+    // Render options on the original image, resuming from the latest cached step
+    pub(crate) fn render(&mut self, effect: &mut GlitchEffect, mut options: GlitchOptions) -> Vec<u8> {
+        effect.prepare_options_internal(&mut options);
+        let mut data = self.original.clone();
+        if options.seed.is_none() {
+            effect.process_internal(&mut data, self.width, &options);
+            return data;
+        }
+
+        let stages = job::stages(&options);
+        let values = serde_json::to_value(&options).unwrap_or_default();
+        let mut keys = Vec::with_capacity(stages.len());
+        let mut previous: Key = Sha256::new().chain_update(self.source).chain_update(values["seed"].to_string()).finalize().into();
+        for &stage in &stages {
+            previous = self.step_key(effect, &previous, &values, &options, stage);
+            keys.push(previous);
+        }
+
+        // Every key chains the ones before it, so the latest cached step is a valid start
+        let mut start = 0;
+        for index in (0..keys.len()).rev() {
+            if let Some(step) = self.lookup(&keys[index]) {
+                data.copy_from_slice(&step.data);
+                effect.rng = SmallRng::seed_from_u64(step.next_seed);
+                start = index + 1;
+                break;
+            }
+        }
+        if start == stages.len() {
+            return data;
+        }
+
+        // Masks come from the pipeline's input, which is the original even when resuming
+        let maps = Maps::new(&self.original, self.width, &options);
+        for (index, &stage) in stages.iter().enumerate().skip(start) {
+            let next_seed = effect.process_stage(&mut data, self.width, &options, stage, &maps);
+            self.store(keys[index], &data, next_seed);
+        }
+        data
+    }
+}
+
+#[wasm_bindgen]
+impl EffectHistory {
+    // Start a history for an image. `cache_mb` limits the memory used for intermediate
+    // results (default: 128 MB).
+    #[wasm_bindgen(constructor)]
+    pub fn new(image_data: ImageData, cache_mb: Option<f64>) -> EffectHistory {
+        let mut history = Self {
+            original: Vec::new(),
+            width: 0,
+            height: 0,
+            source: [0; 32],
+            cache: Vec::new(),
+            cache_limit: cache_bytes(cache_mb.unwrap_or(DEFAULT_CACHE_MB)),
+            clock: 0,
+            entries: Vec::new(),
+            position: 0,
+        };
+        history.set_original(&image_data);
+        history
+    }
+
+    // Switch to another original image, keeping the history but not the cached results
+    pub fn set_image(&mut self, image_data: ImageData) {
+        self.set_original(&image_data);
+    }
+
+    // Add options as the new current entry, dropping anything that could be redone.
+    // Options equal to the current entry are not added again. Returns the new position.
+    pub fn push(&mut self, options_js: JsValue) -> Result<usize, JsValue> {
+        let options: GlitchOptions = serde_wasm_bindgen::from_value(options_js)?;
+        let unchanged = self.position > 0
+            && serde_json::to_value(&options).ok() == serde_json::to_value(&self.entries[self.position - 1]).ok();
+        if !unchanged {
+            self.entries.truncate(self.position);
+            self.entries.push(options);
+            self.position = self.entries.len();
+        }
+        Ok(self.position)
+    }
+
+    // Step back and return the options now current (no effects at the start of the history),
+    // or undefined if there is nothing to undo
+    pub fn undo(&mut self) -> Result<JsValue, JsValue> {
+        if self.position == 0 {
+            return Ok(JsValue::UNDEFINED);
+        }
+        self.position -= 1;
+        self.current()
+    }
+
+    // Step forward again and return the options now current, or undefined at the end
+    pub fn redo(&mut self) -> Result<JsValue, JsValue> {
+        if self.position == self.entries.len() {
+            return Ok(JsValue::UNDEFINED);
+        }
+        self.position += 1;
+        self.current()
+    }
+
+    // Options of the current entry
+    pub fn current(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.current_options())?)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn can_redo(&self) -> bool {
+        self.position < self.entries.len()
+    }
+
+    // The whole undo/redo stack as { entries, position }, e.g. to save a session
+    pub fn snapshot(&self) -> Result<JsValue, JsValue> {
+        let snapshot = Snapshot { entries: self.entries.clone(), position: self.position };
+        Ok(serde_wasm_bindgen::to_value(&snapshot)?)
+    }
+
+    // Replace the undo/redo stack with one from `snapshot`
+    pub fn restore(&mut self, snapshot_js: JsValue) -> Result<(), JsValue> {
+        let snapshot: Snapshot = serde_wasm_bindgen::from_value(snapshot_js)?;
+        if snapshot.position > snapshot.entries.len() {
+            return Err(JsValue::from_str("Snapshot position is past its entries"));
+        }
+        self.entries = snapshot.entries;
+        self.position = snapshot.position;
+        Ok(())
+    }
+
+    // Memory currently used by cached results, in bytes
+    #[wasm_bindgen(getter)]
+    pub fn cache_size(&self) -> usize {
+        self.cache.iter().map(|step| step.data.len()).sum()
+    }
+
+    pub fn set_cache_limit(&mut self, cache_mb: f64) {
+        self.cache_limit = cache_bytes(cache_mb);
+        self.evict(self.cache_limit);
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
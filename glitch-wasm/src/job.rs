@@ -0,0 +1,271 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::ImageData;
+
+use crate::buffer::ImageBuffer;
+use crate::channels::ChannelEffects;
+use crate::masks::{self, Maps};
+use crate::{GlitchEffect, GlitchOptions};
+
+// One effect of the pipeline, in the default order `process_internal` runs them
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stage {
+    PixelSort,
+    DataBend,
+    ChannelShift,
+    Noise,
+    Invert,
+    Quantize,
+    ByteCorrupt,
+    ChunkSwap,
+    BinaryXor,
+    ImageBlend,
+    Sonify,
+    Fourier,
+    BitPlane,
+    Reinterpret,
+    Yuv,
+    ChannelChains,
+    Layers,
+    Transform,
+}
+
+impl Stage {
+    // Reported to JS, matching the option names
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::PixelSort => "pixel_sort",
+            Stage::DataBend => "data_bend",
+            Stage::ChannelShift => "channel_shift",
+            Stage::Noise => "noise",
+            Stage::Invert => "invert",
+            Stage::Quantize => "quantize",
+            Stage::ByteCorrupt => "byte_corrupt",
+            Stage::ChunkSwap => "chunk_swap",
+            Stage::BinaryXor => "binary_xor",
+            Stage::ImageBlend => "image_blend",
+            Stage::Sonify => "sonify",
+            Stage::Fourier => "fourier",
+            Stage::BitPlane => "bit_plane",
+            Stage::Reinterpret => "reinterpret",
+            Stage::Yuv => "yuv",
+            Stage::ChannelChains => "channel_chains",
+            Stage::Layers => "layers",
+            Stage::Transform => "transform",
+        }
+    }
+}
+
+// Enabled stages in the order they run: pipeline order unless `order` rearranges them
+pub fn stages(options: &GlitchOptions) -> Vec<Stage> {
+    let enabled = [
+        (Stage::PixelSort, options.pixel_sort.is_some()),
+        (Stage::DataBend, options.data_bend.is_some()),
+        (Stage::ChannelShift, options.channel_shift.is_some()),
+        (Stage::Noise, options.noise.is_some()),
+        (Stage::Invert, options.invert.is_some()),
+        (Stage::Quantize, options.quantize.is_some()),
+        (Stage::ByteCorrupt, options.byte_corrupt.is_some()),
+        (Stage::ChunkSwap, options.chunk_swap.is_some()),
+        (Stage::BinaryXor, options.binary_xor.is_some()),
+        (Stage::ImageBlend, options.image_blend.is_some()),
+        (Stage::Sonify, options.sonify.is_some()),
+        (Stage::Fourier, options.fourier.is_some()),
+        (Stage::BitPlane, options.bit_plane.is_some()),
+        (Stage::Reinterpret, options.reinterpret.is_some()),
+        (Stage::Yuv, options.yuv.is_some()),
+        (Stage::ChannelChains, !channel_chains(options).is_empty()),
+        (Stage::Layers, options.layers.is_some()),
+        (Stage::Transform, options.transform.is_some()),
+    ];
+    let mut stages: Vec<Stage> = enabled.into_iter().filter(|(_, on)| *on).map(|(stage, _)| stage).collect();
+    if let Some(order) = &options.order {
+        // Stable, so stages missing from `order` keep their relative pipeline order at the end
+        stages.sort_by_key(|stage| order.iter().position(|name| name == stage.name()).unwrap_or(order.len()));
+    }
+    stages
+}
+
+// Per-channel shortcuts (RGBA space) followed by explicit chains
+pub fn channel_chains(options: &GlitchOptions) -> Vec<(usize, usize, &ChannelEffects)> {
+    let mut chains: Vec<(usize, usize, &ChannelEffects)> = [
+        &options.red_channel,
+        &options.green_channel,
+        &options.blue_channel,
+        &options.alpha_channel,
+    ]
+        .into_iter()
+        .enumerate()
+        .filter_map(|(channel, effects)| effects.as_ref().map(|effects| (0, channel, effects)))
+        .collect();
+    for chain in options.channel_chains.iter().flatten() {
+        chains.push((chain.space, chain.channel, &chain.effects));
+    }
+    chains
+}
+
+// Units handed to a splittable stage per step: rows/columns for pixel sort, iterations for
+// data bend. Small enough to keep a step well under a frame on large images.
+const UNITS_PER_STEP: usize = 16;
+
+#[derive(Serialize)]
+struct JobProgress {
+    effect: Option<&'static str>, // Stage currently running, None once finished
+    stage: usize,                 // Index of that stage
+    stages: usize,                // Number of enabled stages
+    effect_percent: f64,          // Progress within the current stage (0-100)
+    percent: f64,                 // Overall progress (0-100)
+    done: bool,
+    cancelled: bool,
+}
+
+// A render split into small steps. Created by `GlitchEffect::start_job` and advanced with
+// `GlitchEffect::run_job`, so JS can yield between steps, show progress and cancel.
+#[wasm_bindgen]
+pub struct RenderJob {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    options: GlitchOptions,
+    stages: Vec<Stage>,
+    stage: usize,        // Index into `stages`
+    unit: usize,         // Units of the current stage already done
+    units: Option<usize>, // Units in the current stage, known once it starts
+    next_seed: u64,      // Random stream for the stages after the current one
+    threshold: Option<u8>, // Pixel sort threshold of the current stage, resolved when it starts
+    maps: Maps,          // Content maps of the input image for masked stages
+    mask: Option<(Vec<f32>, Vec<u8>)>, // Weights and the pixels before the current stage, if it is masked
+    cancelled: bool,
+    callback: Option<js_sys::Function>,
+}
+
+impl RenderJob {
+    pub(crate) fn new(data: Vec<u8>, width: u32, height: u32, options: GlitchOptions) -> Self {
+        let stages = stages(&options);
+        let maps = Maps::new(&data, width, &options);
+        Self {
+            data,
+            width,
+            height,
+            options,
+            stages,
+            stage: 0,
+            unit: 0,
+            units: None,
+            next_seed: 0,
+            threshold: None,
+            maps,
+            mask: None,
+            cancelled: false,
+            callback: None,
+        }
+    }
+
+    // Advance by one step: a batch of units of a splittable stage, or a whole other stage
+    pub(crate) fn step(&mut self, effect: &mut GlitchEffect) {
+        if self.is_finished() { return; }
+        let stage = self.stages[self.stage];
+        let units = match self.units {
+            Some(units) => units,
+            None => {
+                self.next_seed = effect.begin_stage();
+                let units = GlitchEffect::stage_units(stage, self.data.len(), self.width, &self.options);
+                self.units = Some(units);
+                self.threshold = GlitchEffect::stage_threshold(stage, &self.data, self.width, &self.options);
+                self.mask = self.maps.weights(&self.options, stage).map(|weights| (weights, self.data.clone()));
+                units
+            }
+        };
+
+        let end = match stage {
+            Stage::PixelSort | Stage::DataBend => (self.unit + UNITS_PER_STEP).min(units),
+            _ => units,
+        };
+        effect.run_stage(stage, &mut self.data, self.width, &self.options, self.unit..end, self.threshold);
+        self.unit = end;
+
+        if self.unit >= units {
+            if let Some((weights, before)) = self.mask.take() {
+                masks::blend(&mut self.data, &before, &weights);
+            }
+            effect.end_stage(self.next_seed);
+            self.stage += 1;
+            self.unit = 0;
+            self.units = None;
+        }
+    }
+
+    // Nothing left to run, either because every stage ran or the job was cancelled
+    pub(crate) fn is_finished(&self) -> bool {
+        self.cancelled || self.stage >= self.stages.len()
+    }
+
+    pub(crate) fn report(&self) -> Result<(), JsValue> {
+        if let Some(callback) = &self.callback {
+            callback.call1(&JsValue::NULL, &self.progress()?)?;
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl RenderJob {
+    #[wasm_bindgen(getter)]
+    pub fn done(&self) -> bool {
+        self.stage >= self.stages.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    // Stop before the next step, e.g. when a slider moves and the render is stale
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+
+    // Called with the progress object after every `run_job` call
+    pub fn set_progress_callback(&mut self, callback: Option<js_sys::Function>) {
+        self.callback = callback;
+    }
+
+    // { effect, stage, stages, effect_percent, percent, done, cancelled } for polling
+    pub fn progress(&self) -> Result<JsValue, JsValue> {
+        let total = self.stages.len();
+        let effect_fraction = match self.units {
+            Some(units) if units > 0 => self.unit as f64 / units as f64,
+            _ => 0.0,
+        };
+        let overall = if total == 0 {
+            1.0
+        } else {
+            (self.stage as f64 + effect_fraction).min(total as f64) / total as f64
+        };
+        let progress = JobProgress {
+            effect: self.stages.get(self.stage).map(|stage| stage.name()),
+            stage: self.stage.min(total),
+            stages: total,
+            effect_percent: effect_fraction * 100.0,
+            percent: overall * 100.0,
+            done: self.done(),
+            cancelled: self.cancelled,
+        };
+        Ok(serde_wasm_bindgen::to_value(&progress)?)
+    }
+
+    // Current pixels as ImageData. Before the job is done this is a partial render.
+    pub fn image_data(&self) -> Result<ImageData, JsValue> {
+        ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&self.data), self.width, self.height)
+    }
+
+    // Copy the current pixels into a buffer of the same size
+    pub fn copy_to_buffer(&self, buffer: &mut ImageBuffer) -> Result<(), JsValue> {
+        let pixels = buffer.pixels_mut();
+        if pixels.len() != self.data.len() {
+            return Err(JsValue::from_str("Buffer size does not match the job's image"));
+        }
+        pixels.copy_from_slice(&self.data);
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+// Hot per-pixel loops in two forms: a plain scalar reference and a block version that
+// works on fixed 64-byte (16 pixel) arrays with branch-free lane math. The block versions
+// are what the engine uses; LLVM turns them into simd128 on wasm (enabled in
+// .cargo/config.toml) and into the target's baseline SIMD natively, e.g. SSE2 on x86_64.
+// Both forms must produce identical output, which the tests at the end of this file check;
+// `benches/kernels.rs` times them.
+
+use rand::Rng;
+
+pub const LANES: usize = 64;
+
+// 0xFF for RGB lanes, 0x00 for alpha lanes
+const RGB_MASK: [u8; LANES] = {
+    let mut mask = [0xFF; LANES];
+    let mut i = 3;
+    while i < LANES {
+        mask[i] = 0;
+        i += 4;
+    }
+    mask
+};
+
+// Rounded x / 255 for x < 65536 without a division
+#[inline(always)]
+fn div255(x: u32) -> u32 {
+    (x + 128 + ((x + 128) >> 8)) >> 8
+}
+
+// Channel mask for invert: 0xFF for every channel to flip
+pub fn invert_mask(channels: &[usize]) -> [u8; 4] {
+    let mut mask = [0; 4];
+    for &ch in channels {
+        if ch < 4 {
+            mask[ch] = 0xFF;
+        }
+    }
+    mask
+}
+
+#[allow(dead_code)] // Reference for the tests and benches/kernels.rs
+pub fn invert_scalar(data: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        if mask[i % 4] != 0 {
+            *byte = 255 - *byte;
+        }
+    }
+}
+
+pub fn invert(data: &mut [u8], mask: [u8; 4]) {
+    let block_mask: [u8; LANES] = std::array::from_fn(|i| mask[i % 4]);
+    xor_blocks(data, &block_mask);
+}
+
+// XOR every byte with a block-sized repeating mask
+fn xor_blocks(data: &mut [u8], mask: &[u8; LANES]) {
+    let mut blocks = data.chunks_exact_mut(LANES);
+    for block in &mut blocks {
+        let block: &mut [u8; LANES] = block.try_into().unwrap();
+        for i in 0..LANES {
+            block[i] ^= mask[i];
+        }
+    }
+    for (byte, m) in blocks.into_remainder().iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+}
+
+// Lookup table for quantizing to `levels` levels, matching the original f64 rounding
+pub fn quantize_table(levels: usize) -> [u8; 256] {
+    let divisor = 255.0 / (levels.max(2) - 1) as f64;
+    std::array::from_fn(|value| ((value as f64 / divisor).round() * divisor) as u8)
+}
+
+#[allow(dead_code)] // Reference for the tests and benches/kernels.rs
+pub fn quantize_scalar(data: &mut [u8], levels: usize) {
+    let divisor = 255.0 / (levels.max(2) - 1) as f64;
+    for (i, byte) in data.iter_mut().enumerate() {
+        // Skip alpha channel
+        if i % 4 != 3 {
+            *byte = ((*byte as f64 / divisor).round() * divisor) as u8;
+        }
+    }
+}
+
+pub fn quantize(data: &mut [u8], table: &[u8; 256]) {
+    let mut blocks = data.chunks_exact_mut(LANES);
+    for block in &mut blocks {
+        let block: &mut [u8; LANES] = block.try_into().unwrap();
+        for i in 0..LANES {
+            let mapped = table[block[i] as usize];
+            block[i] = (mapped & RGB_MASK[i]) | (block[i] & !RGB_MASK[i]);
+        }
+    }
+    for (i, byte) in blocks.into_remainder().iter_mut().enumerate() {
+        if i % 4 != 3 {
+            *byte = table[*byte as usize];
+        }
+    }
+}
+
+// Noise amounts drawn exactly as the original per-byte loop did, so seeded output is
+// unchanged: for each RGB byte an amount in 0..max, then whether to add or subtract it.
+// Alpha bytes get 0 and take no draws.
+pub fn noise_deltas<R: Rng>(rng: &mut R, len: usize, max: u8) -> Vec<i16> {
+    let mut deltas = vec![0; len];
+    if max == 0 {
+        return deltas;
+    }
+    for pixel in deltas.chunks_mut(4) {
+        for delta in pixel.iter_mut().take(3) {
+            let amount = rng.gen_range(0..max) as i16;
+            *delta = if rng.gen_bool(0.5) { amount } else { -amount };
+        }
+    }
+    deltas
+}
+
+// Noise: each byte moves by its delta, saturating at 0 and 255. Alpha bytes are left alone.
+pub fn noise_scalar(data: &mut [u8], deltas: &[i16]) {
+    for (i, (byte, &delta)) in data.iter_mut().zip(deltas).enumerate() {
+        if i % 4 != 3 {
+            *byte = (*byte as i16 + delta).clamp(0, 255) as u8;
+        }
+    }
+}
+
+pub fn noise(data: &mut [u8], deltas: &[i16]) {
+    let deltas = &deltas[..data.len().min(deltas.len())];
+    let mut blocks = data.chunks_exact_mut(LANES);
+    let mut delta_blocks = deltas.chunks_exact(LANES);
+    for (block, delta) in (&mut blocks).zip(&mut delta_blocks) {
+        let block: &mut [u8; LANES] = block.try_into().unwrap();
+        let delta: &[i16; LANES] = delta.try_into().unwrap();
+        for i in 0..LANES {
+            let noisy = (block[i] as i16 + delta[i]).clamp(0, 255) as u8;
+            block[i] = (noisy & RGB_MASK[i]) | (block[i] & !RGB_MASK[i]);
+        }
+    }
+    noise_scalar(blocks.into_remainder(), delta_blocks.remainder());
+}
+
+// XOR with a repeating pattern, starting `phase` bytes into it
+#[allow(dead_code)] // Reference for the tests and benches/kernels.rs
+pub fn xor_pattern_scalar(data: &mut [u8], values: &[u8], phase: usize) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= values[(phase + i) % values.len()];
+    }
+}
+
+pub fn xor_pattern(data: &mut [u8], values: &[u8], phase: usize) {
+    if values.is_empty() { return; }
+    // Unroll the pattern to a whole number of blocks so the phase is the same at every block
+    let period = values.len() * LANES;
+    let unrolled: Vec<u8> = (0..period).map(|i| values[(phase + i) % values.len()]).collect();
+    for chunk in data.chunks_mut(period) {
+        for (byte, value) in chunk.iter_mut().zip(&unrolled) {
+            *byte ^= value;
+        }
+    }
+}
+
+// XOR a buffer with a same-length mask
+pub fn xor_bytes(data: &mut [u8], mask: &[u8]) {
+    for (byte, m) in data.iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+}
+
+// Fixed-point blend amount (0-256) from a 0.0-1.0 amount
+pub fn blend_amount(amount: f64) -> u32 {
+    (amount.clamp(0.0, 1.0) * 256.0).round() as u32
+}
+
+// One blended channel: 0=mix, 1=difference, 2=multiply, 3=screen, 4=overlay, then
+// mixed with the primary by the fixed-point amount, rounding to nearest
+#[inline(always)]
+fn blend_byte(p: u32, s: u32, mode: usize, amount: u32) -> u32 {
+    let blended = match mode {
+        0 => s,
+        1 => p.max(s) - p.min(s),
+        2 => div255(p * s),
+        3 => 255 - div255((255 - p) * (255 - s)),
+        _ => {
+            if p < 128 {
+                div255(2 * p * s)
+            } else {
+                255 - div255(2 * (255 - p) * (255 - s))
+            }
+        }
+    };
+    (blended * amount + p * (256 - amount) + 128) >> 8
+}
+
+pub fn blend_scalar(row: &mut [u8], secondary: &[u8], mode: usize, amount: u32) {
+    if mode > 4 { return; }
+    for (i, (p, &s)) in row.iter_mut().zip(secondary).enumerate() {
+        if i % 4 != 3 {
+            *p = blend_byte(*p as u32, s as u32, mode, amount) as u8;
+        }
+    }
+}
+
+pub fn blend(row: &mut [u8], secondary: &[u8], mode: usize, amount: u32) {
+    if mode > 4 { return; }
+    let secondary = &secondary[..row.len().min(secondary.len())];
+    let mut blocks = row.chunks_exact_mut(LANES);
+    let mut secondary_blocks = secondary.chunks_exact(LANES);
+    for (block, other) in (&mut blocks).zip(&mut secondary_blocks) {
+        let block: &mut [u8; LANES] = block.try_into().unwrap();
+        let other: &[u8; LANES] = other.try_into().unwrap();
+        // Dispatch once per block so each inner loop is a single straight-line kernel
+        match mode {
+            0 => blend_block::<0>(block, other, amount),
+            1 => blend_block::<1>(block, other, amount),
+            2 => blend_block::<2>(block, other, amount),
+            3 => blend_block::<3>(block, other, amount),
+            _ => blend_block::<4>(block, other, amount),
+        }
+    }
+    blend_scalar(blocks.into_remainder(), secondary_blocks.remainder(), mode, amount);
+}
+
+#[inline(always)]
+fn blend_block<const MODE: usize>(block: &mut [u8; LANES], other: &[u8; LANES], amount: u32) {
+    for i in 0..LANES {
+        let p = block[i] as u32;
+        let blended = blend_byte(p, other[i] as u32, MODE, amount) as u8;
+        block[i] = (blended & RGB_MASK[i]) | (block[i] & !RGB_MASK[i]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    // Byte lengths around the block size, then whole rows of odd pixel widths
+    const LENGTHS: [usize; 10] = [0, 1, 63, 64, 65, 4, 12, 68, 132, 1004];
+
+    fn random_bytes(rng: &mut SmallRng, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        rng.fill(&mut bytes[..]);
+        bytes
+    }
+
+    fn assert_same<S, V>(name: &str, mut scalar: S, mut block: V)
+    where
+        S: FnMut(&mut [u8]),
+        V: FnMut(&mut [u8]),
+    {
+        let mut rng = SmallRng::seed_from_u64(0x61177c4);
+        for len in LENGTHS {
+            let input = random_bytes(&mut rng, len);
+            let (mut expected, mut actual) = (input.clone(), input);
+            scalar(&mut expected);
+            block(&mut actual);
+            assert_eq!(expected, actual, "{name}: scalar and block output differ at length {len}");
+        }
+    }
+
+    #[test]
+    fn invert_matches_scalar() {
+        for channels in [&[0, 2][..], &[0, 1, 2, 3], &[3], &[]] {
+            let mask = invert_mask(channels);
+            assert_same("invert", |d| invert_scalar(d, mask), |d| invert(d, mask));
+        }
+    }
+
+    #[test]
+    fn quantize_matches_scalar() {
+        for levels in [2, 3, 5, 16, 255] {
+            let table = quantize_table(levels);
+            assert_same("quantize", |d| quantize_scalar(d, levels), |d| quantize(d, &table));
+        }
+    }
+
+    #[test]
+    fn noise_matches_scalar() {
+        for max in [0, 1, 77, 255] {
+            let deltas = noise_deltas(&mut SmallRng::seed_from_u64(7), 1004, max);
+            assert_same("noise", |d| noise_scalar(d, &deltas), |d| noise(d, &deltas));
+        }
+    }
+
+    // Same output as the per-byte loop noise used before it was vectorized
+    #[test]
+    fn noise_matches_original_loop() {
+        let input = random_bytes(&mut SmallRng::seed_from_u64(3), 1004);
+        for max in [1, 77, 255] {
+            let mut expected = input.clone();
+            let mut rng = SmallRng::seed_from_u64(max as u64);
+            for i in (0..expected.len()).step_by(4) {
+                for j in 0..3 {
+                    let noise = rng.gen_range(0..max);
+                    if rng.gen_bool(0.5) {
+                        expected[i + j] = expected[i + j].saturating_add(noise);
+                    } else {
+                        expected[i + j] = expected[i + j].saturating_sub(noise);
+                    }
+                }
+            }
+            let deltas = noise_deltas(&mut SmallRng::seed_from_u64(max as u64), input.len(), max);
+            let mut actual = input.clone();
+            noise(&mut actual, &deltas);
+            assert_eq!(expected, actual, "max {max}");
+        }
+    }
+
+    #[test]
+    fn xor_pattern_matches_scalar() {
+        for (pattern, phase) in [(&[0x5a, 0x13, 0xff, 0x00, 0x81][..], 3), (&[0xff], 0), (&[1, 2, 3], 2)] {
+            assert_same("xor", |d| xor_pattern_scalar(d, pattern, phase), |d| xor_pattern(d, pattern, phase));
+        }
+    }
+
+    #[test]
+    fn blend_matches_scalar() {
+        let mut rng = SmallRng::seed_from_u64(11);
+        let secondary = random_bytes(&mut rng, 1004);
+        for mode in 0..5 {
+            for amount in [0.0, 0.3, 1.0] {
+                let amount = blend_amount(amount);
+                assert_same("blend", |d| blend_scalar(d, &secondary, mode, amount), |d| blend(d, &secondary, mode, amount));
+            }
+        }
+    }
+}
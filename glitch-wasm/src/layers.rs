@@ -0,0 +1,160 @@
+// Non-destructive layer compositor. Each layer has its own pixels (the incoming image, a
+// registered image, a solid color or generated noise), runs its own effect chain on them and
+// is composited over the layers below with a mask, an opacity and a blend mode. The stack is
+// a pipeline step, so the incoming image is everything the pipeline did before it.
+//
+// Output from the app's WebGL shader stack joins the stack as an image layer: render the
+// shader to a canvas, register its pixels with `register_image` (or `update_image` per frame)
+// and reference the handle.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::luminance;
+use crate::blend::{self, Compositing};
+use crate::buffer::{ImageStore, SecondaryImage};
+use crate::masks::{Maps, Shape};
+use crate::modulation::hash64;
+use crate::{scale_offset, GlitchEffect, GlitchOptions};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LayerSource {
+    Original,                                                // The image as the layer stack receives it
+    Image {
+        image: Option<u32>,                                  // Handle from register_image
+        asset: Option<String>,                               // Content hash of a registered image, used without a handle
+        offset_x: Option<i32>,                               // Offset in pixels, scaled with the image; the image tiles
+        offset_y: Option<i32>,
+    },
+    Color { color: [u8; 4] },                                // Solid RGBA color
+    Noise {
+        seed: Option<u64>,                                   // Fixed noise (default: drawn from the render's seed)
+        size: Option<f64>,                                   // Cell size in pixels, scaled with the image (default: 1)
+        monochrome: Option<bool>,                            // Gray noise instead of color noise
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LayerMask {
+    pub(crate) source: Option<usize>,  // Content map of the incoming image, as for `masks`: 0=Sobel edges, 1=Canny edges, 2=local contrast, 3=saliency, 4=skin tone
+    pub(crate) image: Option<u32>,     // Registered image whose luminance is the mask, instead of a content map
+    pub(crate) mode: Option<usize>,    // 0=threshold the map, 1=use it as-is (default: 0)
+    pub(crate) invert: Option<bool>,
+    pub(crate) threshold: Option<f64>, // Cutoff for mode 0 (0.0-1.0, default: 0.5)
+    pub(crate) softness: Option<f64>,  // Feather radius in pixels, scaled with the image (default: 0)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Layer {
+    pub(crate) name: Option<String>,
+    pub(crate) source: LayerSource,
+    pub(crate) effects: Option<GlitchOptions>, // Effects run on the layer's pixels before compositing
+    pub(crate) mask: Option<LayerMask>,
+    pub(crate) opacity: Option<f64>,           // 0.0-1.0 (default: 1)
+    pub(crate) blend_mode: Option<usize>,      // See blend.rs: 0=normal, 1=difference, 2=multiply, 3=screen, 4=overlay, 5=add, ... 25=lighter color (default: 0)
+    pub(crate) operator: Option<usize>,        // Porter-Duff operator, see blend.rs (default: 0=source-over)
+    pub(crate) linear: Option<bool>,           // Blend in linear light (default: false)
+    pub(crate) enabled: Option<bool>,          // Hidden layers are skipped (default: true)
+}
+
+fn registered<'a>(images: &'a ImageStore, image: Option<u32>, asset: &Option<String>, offset_x: i32, offset_y: i32) -> Option<SecondaryImage<'a>> {
+    match (image, asset) {
+        (Some(handle), _) => images.get(handle, offset_x, offset_y),
+        (None, Some(hash)) => images.find(hash, offset_x, offset_y),
+        (None, None) => None,
+    }
+}
+
+// Fill a layer with a registered image, tiled over the canvas
+fn tile(pixels: &mut [u8], width: usize, image: &SecondaryImage) {
+    for (y, row) in pixels.chunks_exact_mut(width * 4).enumerate() {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&image.pixel(x, y));
+        }
+    }
+}
+
+// Value noise on square cells, so it keeps its look when the cell size scales with the image
+fn noise(pixels: &mut [u8], width: usize, seed: u64, cell: usize, monochrome: bool) {
+    for (y, row) in pixels.chunks_exact_mut(width * 4).enumerate() {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let cell_index = ((y / cell) as u64) << 32 | (x / cell) as u64;
+            let h = hash64(seed ^ hash64(cell_index));
+            let rgb = if monochrome { [h as u8; 3] } else { [h as u8, (h >> 8) as u8, (h >> 16) as u8] };
+            pixel.copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+        }
+    }
+}
+
+// A layer's pixels before its effects, or None if its image isn't registered
+fn layer_pixels(effect: &mut GlitchEffect, source: &LayerSource, input: &[u8], width: usize, scale: f64) -> Option<Vec<u8>> {
+    let mut pixels = vec![0; input.len()];
+    match source {
+        LayerSource::Original => pixels.copy_from_slice(input),
+        LayerSource::Image { image, asset, offset_x, offset_y } => {
+            let offset_x = scale_offset(offset_x.unwrap_or(0), scale);
+            let offset_y = scale_offset(offset_y.unwrap_or(0), scale);
+            tile(&mut pixels, width, &registered(&effect.images, *image, asset, offset_x, offset_y)?);
+        }
+        LayerSource::Color { color } => {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.copy_from_slice(color);
+            }
+        }
+        LayerSource::Noise { seed, size, monochrome } => {
+            // Draw even with a fixed seed, so later layers don't depend on the setting
+            let drawn: u64 = effect.rng.gen();
+            let cell = ((size.unwrap_or(1.0) * scale).round() as usize).max(1);
+            noise(&mut pixels, width, seed.unwrap_or(drawn), cell, monochrome.unwrap_or(false));
+        }
+    }
+    Some(pixels)
+}
+
+// Weights for a layer mask, or None if it has no usable map
+fn layer_mask(images: &ImageStore, mask: &LayerMask, maps: &Maps, len: usize, width: usize) -> Option<Vec<f32>> {
+    let luma: Vec<f32>;
+    let map = match mask.image {
+        Some(handle) => {
+            let mut pixels = vec![0; len];
+            tile(&mut pixels, width, &images.get(handle, 0, 0)?);
+            luma = luminance(&pixels).into_iter().map(|value| value as f32 / 255.0).collect();
+            &luma[..]
+        }
+        None => maps.map(mask.source?)?,
+    };
+    Some(maps.shape(map, &Shape {
+        mode: mask.mode.unwrap_or(0),
+        invert: mask.invert.unwrap_or(false),
+        threshold: mask.threshold.unwrap_or(0.5) as f32,
+        radius: maps.radius(mask.softness),
+    }))
+}
+
+// Composite the layers bottom to top over the image
+pub fn composite(effect: &mut GlitchEffect, data: &mut [u8], width: u32, layers: &[Layer], scale: f64) {
+    if width == 0 { return; }
+    let input = data.to_vec();
+    let sources = layers.iter().filter_map(|layer| layer.mask.as_ref().and_then(|mask| mask.source));
+    let maps = Maps::with_sources(&input, width, scale, sources);
+
+    for layer in layers.iter().filter(|layer| layer.enabled.unwrap_or(true)) {
+        let Some(mut pixels) = layer_pixels(effect, &layer.source, &input, width as usize, scale) else { continue; };
+        if let Some(effects) = &layer.effects {
+            effect.process_internal(&mut pixels, width, effects);
+        }
+        let mask = layer.mask.as_ref()
+            .and_then(|mask| layer_mask(&effect.images, mask, &maps, input.len(), width as usize));
+        let opacity = layer.opacity.unwrap_or(1.0).clamp(0.0, 1.0) as f32;
+        let compositing = Compositing {
+            mode: layer.blend_mode.unwrap_or(0),
+            operator: layer.operator.unwrap_or(0),
+            linear: layer.linear.unwrap_or(false),
+        };
+        for (i, (backdrop, source)) in data.chunks_exact_mut(4).zip(pixels.chunks_exact(4)).enumerate() {
+            let weight = mask.as_ref().map_or(1.0, |mask| mask[i]);
+            blend::composite(backdrop, source, &compositing, opacity * weight);
+        }
+    }
+}
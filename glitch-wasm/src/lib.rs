@@ -3,16 +3,66 @@ use web_sys::ImageData;
 use rand::{Rng, SeedableRng};
 use rand::rngs::SmallRng;
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
 
-#[derive(Serialize, Deserialize)]
+mod analysis;
+mod audio;
+mod bitplane;
+mod blend;
+mod buffer;
+mod channels;
+mod evolve;
+mod fft;
+mod fourier;
+mod history;
+mod job;
+mod kernels;
+mod layers;
+mod masks;
+mod modulation;
+mod proxy;
+mod randomize;
+mod recipe;
+mod reinterpret;
+mod sonify;
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+mod threads;
+mod tiles;
+mod transform;
+mod yuv;
+
+use audio::{AudioFeatures, AudioMapping};
+use bitplane::BitPlaneOptions;
+use blend::Compositing;
+use buffer::{ImageStore, Placement, SecondaryImage};
+use channels::{ChannelChain, ChannelEffects};
+use fourier::FourierOptions;
+use job::Stage;
+use layers::Layer;
+use masks::StageMask;
+use modulation::ModulationRoute;
+use reinterpret::ReinterpretOptions;
+use sonify::SonifyOptions;
+use transform::TransformOptions;
+use yuv::YuvOptions;
+
+pub use audio::{analyze_wav, AudioAnalyzer};
+pub use buffer::ImageBuffer;
+pub use evolve::Population;
+pub use history::EffectHistory;
+pub use job::RenderJob;
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PixelSortOptions {
     intensity: f64,
     threshold: f64,
     vertical: bool,
     channel: Option<usize>, // 0=R, 1=G, 2=B, None=brightness
+    auto_threshold: Option<usize>, // Pick the threshold from the image: 0=Otsu, 1=percentile of the sort key, 2=target fraction of sorted pixels, None=use `threshold`
+    auto_target: Option<f64>,      // Percentile (mode 1) or fraction of pixels to sort (mode 2), 0.0-1.0 (default: 0.5)
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct DataBendOptions {
     amount: f64,
     mode: Option<usize>, // 0=duplicate, 1=reverse, 2=shift, 3=scramble, None=random
@@ -20,47 +70,131 @@ pub struct DataBendOptions {
     channel: Option<usize>, // 0=R, 1=G, 2=B, 3=A, None=all
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ChannelShiftOptions {
     amount: f64,
     channels: Option<Vec<usize>>, // Which channels to shift
     direction: Option<i32>, // -1=left, 1=right, 0=random, None=random
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ByteCorruptOptions {
     amount: f64,               // Corruption intensity (0.0-1.0)
     mode: Option<usize>,       // 0=random bytes, 1=bit flip, 2=zero out, 3=max out, None=random
-    block_size: Option<usize>, // Size of corruption blocks (default: 1)
+    block_size: Option<usize>, // Size of corruption blocks in bytes, scaled with the image area (default: 1)
     structured: bool,          // Use structured patterns vs random
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ChunkSwapOptions {
     amount: f64,                // How many chunks to swap (0.0-1.0)
     chunk_size: Option<f64>,    // Relative chunk size (0.0-1.0)
     preserve_alpha: bool,       // Whether to preserve alpha channel
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BinaryXorOptions {
     pattern: Option<Vec<u8>>,  // Pattern to XOR with (if None, will use random pattern)
     strength: f64,             // Strength of the effect (0.0-1.0)
     mode: Option<usize>,       // 0=full image, 1=horizontal bands, 2=vertical bands, 3=blocks
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ImageBlendOptions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     secondary_data: Vec<u8>,   // Raw pixel data of secondary image
+    #[serde(default)]
     width: u32,                // Width of secondary image
+    #[serde(default)]
     height: u32,               // Height of secondary image
-    blend_mode: usize,         // 0=mix, 1=difference, 2=multiply, 3=screen, 4=overlay
-    amount: f64,               // Blend intensity (0.0-1.0)
+    secondary_image: Option<u32>, // Handle from register_image, used instead of secondary_data
+    secondary_asset: Option<String>, // Content hash of a registered image (recipe asset reference)
+    blend_mode: usize,         // 0=normal, 1=difference, 2=multiply, 3=screen, 4=overlay, 5-25 see blend.rs
+    operator: Option<usize>,   // Porter-Duff operator, see blend.rs (default: 0=source-over)
+    linear: Option<bool>,      // Blend in linear light (default: false)
+    amount: f64,               // Blend intensity (0.0-1.0), scales the secondary image's alpha
     offset_x: i32,             // Horizontal offset
     offset_y: i32,             // Vertical offset
+    fit: Option<usize>,        // 0=tile at native size, 1=stretch, 2=cover, 3=contain, 4=mirror tile, 5=native size once with transparent surroundings (default: 0)
+    scale: Option<f64>,        // Size multiplier on top of the fit (default: 1)
+    rotation: Option<f64>,     // Degrees clockwise around the secondary image's center
+    flip_x: Option<bool>,      // Mirror the secondary image horizontally
+    flip_y: Option<bool>,      // Mirror the secondary image vertically
+    resample: Option<usize>,   // 0=nearest, 1=bilinear, 2=bicubic, 3=Lanczos (default: 0)
+}
+
+impl ImageBlendOptions {
+    // Resolve the secondary image, preferring a registered handle over inline data, and
+    // place it on a primary of `width` and `len` bytes
+    fn secondary<'a>(&'a self, images: &'a ImageStore, width: u32, len: usize, scale: f64) -> Option<SecondaryImage<'a>> {
+        let offset_x = scale_offset(self.offset_x, scale);
+        let offset_y = scale_offset(self.offset_y, scale);
+        let secondary = match (self.secondary_image, &self.secondary_asset) {
+            (Some(handle), _) => images.get(handle, offset_x, offset_y),
+            (None, Some(hash)) => images.find(hash, offset_x, offset_y),
+            (None, None) => SecondaryImage::new(&self.secondary_data, self.width, self.height, offset_x, offset_y),
+        }?;
+        let placement = Placement {
+            fit: self.fit.unwrap_or(0),
+            scale: self.scale.unwrap_or(1.0),
+            rotation: self.rotation.unwrap_or(0.0),
+            flip_x: self.flip_x.unwrap_or(false),
+            flip_y: self.flip_y.unwrap_or(false),
+            filter: self.resample.unwrap_or(0),
+        };
+        Some(secondary.placed(&placement, width as usize, len / 4 / (width as usize).max(1)))
+    }
+
+    fn compositing(&self) -> Compositing {
+        Compositing {
+            mode: self.blend_mode,
+            operator: self.operator.unwrap_or(0),
+            linear: self.linear.unwrap_or(false),
+        }
+    }
+}
+
+// Image size (geometric mean of width and height) at which relative spatial parameters
+// equal their values in pixels
+const REFERENCE_SIZE: f64 = 1000.0;
+
+// Factor for pixel-sized parameters so that a recipe looks the same at any resolution.
+// Only the pixel count matters, which lets effects without a width use it too.
+fn spatial_scale(len: usize, absolute: bool) -> f64 {
+    if absolute {
+        1.0
+    } else {
+        ((len / 4) as f64).sqrt() / REFERENCE_SIZE
+    }
+}
+
+fn scale_offset(offset: i32, scale: f64) -> i32 {
+    (offset as f64 * scale).round() as i32
+}
+
+// Byte offset of the pixel at (x, y) given as fractions (0.0-1.0) of the width and height,
+// so it is the same point of the picture at any resolution. Clamped to `max` bytes.
+fn pixel_position(x: f64, y: f64, width: usize, len: usize, max: usize) -> usize {
+    let width = width.clamp(1, (len / 4).max(1));
+    let height = (len / 4 / width).max(1);
+    let column = ((x * width as f64) as usize).min(width - 1);
+    let row = ((y * height as f64) as usize).min(height - 1);
+    ((row * width + column) * 4).min(max / 4 * 4)
+}
+
+fn corrupt_bytes(bytes: &mut [u8], mode: usize, rng: &mut SmallRng) {
+    for byte in bytes {
+        match mode {
+            0 => *byte = rng.gen(), // Random bytes
+            1 => *byte ^= 1 << rng.gen_range(0..8), // Bit flip
+            2 => *byte = 0, // Zero out
+            3 => *byte = 255, // Max out
+            _ => *byte = rng.gen(), // Default to random
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct GlitchOptions {
     pixel_sort: Option<PixelSortOptions>,
     data_bend: Option<DataBendOptions>,
@@ -72,11 +206,197 @@ pub struct GlitchOptions {
     chunk_swap: Option<ChunkSwapOptions>,
     binary_xor: Option<BinaryXorOptions>,
     image_blend: Option<ImageBlendOptions>,
+    sonify: Option<SonifyOptions>,           // Audio DSP applied to the pixel data
+    fourier: Option<FourierOptions>,         // Frequency-domain effects
+    bit_plane: Option<BitPlaneOptions>,      // Bit plane isolate/shift/rotate/swap/invert/shuffle
+    reinterpret: Option<ReinterpretOptions>, // Decode the buffer with the wrong pixel format or stride
+    yuv: Option<YuvOptions>,                 // Chroma subsampling, chroma shift and per-plane effects in YCbCr
+    red_channel: Option<ChannelEffects>,     // Effects applied to the red channel only
+    green_channel: Option<ChannelEffects>,   // Effects applied to the green channel only
+    blue_channel: Option<ChannelEffects>,    // Effects applied to the blue channel only
+    alpha_channel: Option<ChannelEffects>,   // Effects applied to the alpha channel only
+    channel_chains: Option<Vec<ChannelChain>>, // Effects applied to RGBA, HSL or YCbCr planes
+    seed: Option<u64>,                       // Reseeds the RNG so the same options give the same result
+    time: Option<f64>,                       // Animation time in seconds for modulation
+    modulation: Option<Vec<ModulationRoute>>, // Modulators routed to effect parameters
+    audio: Option<AudioFeatures>,            // Audio features for this frame (from AudioAnalyzer or analyze_wav)
+    audio_mapping: Option<Vec<AudioMapping>>, // Audio feature sources routed to effect parameters
+    absolute_units: Option<bool>,            // Spatial parameters in pixels at any resolution (default: relative to image size)
+    order: Option<Vec<String>>,              // Stage names in the order to run them; unlisted stages follow in pipeline order
+    masks: Option<Vec<StageMask>>,           // Content-aware masks limiting where effects apply
+    layers: Option<Vec<Layer>>,              // Layer stack composited over the image, bottom to top
+    transform: Option<TransformOptions>,     // Crop, flip, rotation, zoom, affine and perspective warp at the same size
+}
+
+fn sort_key(pixel: &[u8], channel: Option<usize>) -> u8 {
+    match channel {
+        Some(c) if c < 3 => pixel[c],
+        _ => ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8,
+    }
+}
+
+// Pixels a sort with this threshold would move: those in runs of keys at or below it that
+// are at least `min_segment` long (and at least one pixel)
+fn sorted_pixels(keys: &[u8], width: usize, vertical: bool, threshold: u8, min_segment: usize) -> usize {
+    let height = keys.len() / width.max(1);
+    let (lines, length) = if vertical { (width, height) } else { (height, width) };
+    let key = |line: usize, i: usize| if vertical { keys[i * width + line] } else { keys[line * width + i] };
+    let min_run = min_segment.max(1);
+    let mut sorted = 0;
+    for line in 0..lines {
+        let mut run = 0;
+        for i in 0..length {
+            if key(line, i) <= threshold {
+                run += 1;
+                continue;
+            }
+            if run >= min_run { sorted += run; }
+            run = 0;
+        }
+        if run >= min_run { sorted += run; }
+    }
+    sorted
+}
+
+// Minimum segment size a pixel sort moves, scaled with intensity
+fn min_segment(options: &PixelSortOptions, scale: f64) -> usize {
+    (options.intensity * 100.0 * scale) as usize
+}
+
+// Threshold (0-255) for a pixel sort: the fixed option, or picked from the image's sort keys
+fn resolve_threshold(data: &[u8], width: u32, options: &PixelSortOptions, min_segment: usize) -> u8 {
+    let mode = match options.auto_threshold {
+        Some(mode) => mode,
+        None => return (options.threshold * 255.0) as u8,
+    };
+    let keys: Vec<u8> = data.chunks_exact(4).map(|pixel| sort_key(pixel, options.channel)).collect();
+    let target = options.auto_target.unwrap_or(0.5).clamp(0.0, 1.0);
+    match mode {
+        0 => analysis::otsu(&analysis::histogram(keys.iter().copied())),
+        1 => analysis::percentile(&analysis::histogram(keys.iter().copied()), target),
+        _ => {
+            // The sorted fraction only grows with the threshold, so binary search the lowest
+            // threshold that sorts at least the target fraction
+            let wanted = (target * keys.len() as f64).ceil() as usize;
+            let (mut low, mut high) = (0u16, 255u16);
+            while low < high {
+                let mid = (low + high) / 2;
+                if sorted_pixels(&keys, width as usize, options.vertical, mid as u8, min_segment) >= wanted {
+                    high = mid;
+                } else {
+                    low = mid + 1;
+                }
+            }
+            low as u8
+        }
+    }
+}
+
+// Sort the segments of a single row of pixels that fall below the threshold
+fn sort_row(row: &mut [u8], options: &PixelSortOptions, threshold: u8, min_segment: usize) {
+    let row_end = row.len();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    
+    // First, collect segments to sort
+    for x in (0..row_end).step_by(4) {
+        let value = match options.channel {
+            Some(0) => row[x],
+            Some(1) => row[x + 1],
+            Some(2) => row[x + 2],
+            _ => ((row[x] as u16 + row[x + 1] as u16 + row[x + 2] as u16) / 3) as u8
+        };
+        
+        if value > threshold || x + 4 >= row_end {
+            if x > start && x - start >= min_segment * 4 {
+                segments.push((start, x));
+            }
+            start = x + 4;
+        }
+    }
+    
+    // Then sort each segment
+    for (start, end) in segments {
+        let mut pixels: Vec<_> = (start..end)
+            .step_by(4)
+            .map(|i| [row[i], row[i + 1], row[i + 2], row[i + 3]])
+            .collect();
+        
+        pixels.sort_by_key(|pixel| {
+            match options.channel {
+                Some(0) => pixel[0],
+                Some(1) => pixel[1],
+                Some(2) => pixel[2],
+                _ => ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8
+            }
+        });
+        
+        // Write back sorted pixels
+        for (idx, pixel) in pixels.iter().enumerate() {
+            let pos = start + idx * 4;
+            row[pos..pos + 4].copy_from_slice(pixel);
+        }
+    }
+}
+
+// Sort the segments of column `x`, copied out top to bottom into `column`. Segment lengths are
+// measured between byte offsets in the full image, as when columns were sorted in place.
+fn sort_column(column: &mut [u8], x: usize, width: usize, options: &PixelSortOptions, threshold: u8, min_segment: usize) {
+    let height = column.len() / 4;
+    let stride = width * 4;
+    let mut segments = Vec::new();
+    let mut first = 0;
+
+    for y in 0..height {
+        let idx = y * 4;
+        let value = match options.channel {
+            Some(0) => column[idx],
+            Some(1) => column[idx + 1],
+            Some(2) => column[idx + 2],
+            _ => ((column[idx] as u16 + column[idx + 1] as u16 + column[idx + 2] as u16) / 3) as u8
+        };
+
+        if value > threshold || y == height - 1 {
+            let start = first * stride + x * 4;
+            let y_pos = y * stride;
+            if y_pos > start && y_pos - start >= min_segment * 4 {
+                segments.push((first, y));
+            }
+            first = y + 1;
+        }
+    }
+
+    // Segments include the pixel that ended them
+    for (first, last) in segments {
+        let segment = &mut column[first * 4..(last + 1) * 4];
+        let mut pixels: Vec<[u8; 4]> = segment.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]).collect();
+
+        pixels.sort_by_key(|pixel| {
+            match options.channel {
+                Some(0) => pixel[0],
+                Some(1) => pixel[1],
+                Some(2) => pixel[2],
+                _ => ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8
+            }
+        });
+
+        for (dst, pixel) in segment.chunks_exact_mut(4).zip(pixels) {
+            dst.copy_from_slice(&pixel);
+        }
+    }
 }
 
 #[wasm_bindgen]
 pub struct GlitchEffect {
     rng: SmallRng,
+    images: ImageStore,
+    sort_threshold: Option<u8>, // Threshold the latest pixel sort used, for `last_sort_threshold`
+}
+
+impl Default for GlitchEffect {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -84,140 +404,105 @@ impl GlitchEffect {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
         let rng = SmallRng::from_entropy();
-        Self { rng }
+        Self { rng, images: ImageStore::default(), sort_threshold: None }
     }
 
     // Note: These internal functions are not exposed directly to JavaScript
-    fn pixel_sort_internal(&mut self, data: &mut [u8], width: u32, options: &PixelSortOptions) {
+    fn pixel_sort_internal(&mut self, data: &mut [u8], width: u32, options: &PixelSortOptions, scale: f64) {
+        let lines = Self::pixel_sort_lines(data.len(), width, options);
+        let threshold = resolve_threshold(data, width, options, min_segment(options, scale));
+        self.pixel_sort_range(data, width, options, threshold, 0..lines, scale);
+    }
+
+    // Rows (horizontal) or columns (vertical) a pixel sort works through independently
+    fn pixel_sort_lines(len: usize, width: u32, options: &PixelSortOptions) -> usize {
+        if width == 0 { return 0; }
+        if options.vertical {
+            width as usize
+        } else {
+            len.div_ceil(width as usize * 4)
+        }
+    }
+
+    // Pixel sort a range of rows or columns, so long sorts can be split up. The threshold is
+    // resolved by the caller once for the whole sort.
+    fn pixel_sort_range(&mut self, data: &mut [u8], width: u32, options: &PixelSortOptions, threshold: u8,
+                        lines: Range<usize>, scale: f64) {
+        self.sort_threshold = Some(threshold);
+        if width == 0 { return; }
         let height = (data.len() / 4) as u32 / width;
-        let threshold = (options.threshold * 255.0) as u8;
-        let min_segment = (options.intensity * 100.0) as usize; // Minimum segment size scales with intensity
+        let min_segment = min_segment(options, scale);
         
         if options.vertical {
-            // Vertical sorting
-            for x in 0..width {
-                let mut segments = Vec::new();
-                let mut start = (x * 4) as usize;
-                
-                for y in 0..height {
-                    let idx = (y * width * 4 + x * 4) as usize;
-                    let value = match options.channel {
-                        Some(0) => data[idx],
-                        Some(1) => data[idx + 1],
-                        Some(2) => data[idx + 2],
-                        _ => ((data[idx] as u16 + data[idx + 1] as u16 + data[idx + 2] as u16) / 3) as u8
-                    };
-                    
-                    if value > threshold || y == height - 1 {
-                        let y_pos = (y * width * 4) as usize;
-                        if y_pos > start && y_pos - start >= min_segment * 4 {
-                            segments.push((start, idx));
-                        }
-                        start = ((y + 1) * width * 4 + x * 4) as usize;
-                    }
-                }
-                
-                // Sort each vertical segment
-                for (start_idx, end_idx) in segments {
-                    let mut pixels = Vec::new();
-                    let mut i = start_idx;
-                    while i <= end_idx {
-                        let pixel = [data[i], data[i + 1], data[i + 2], data[i + 3]];
-                        pixels.push(pixel);
-                        i += (width * 4) as usize;
-                    }
-                    
-                    pixels.sort_by_key(|pixel| {
-                        match options.channel {
-                            Some(0) => pixel[0],
-                            Some(1) => pixel[1],
-                            Some(2) => pixel[2],
-                            _ => ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8
-                        }
-                    });
-                    
-                    // Write back sorted pixels
-                    let mut i = start_idx;
-                    for pixel in pixels {
-                        data[i] = pixel[0];
-                        data[i + 1] = pixel[1];
-                        data[i + 2] = pixel[2];
-                        data[i + 3] = pixel[3];
-                        i += (width * 4) as usize;
-                    }
+            // Vertical sorting: copy the columns out so each one is contiguous and they can
+            // be sorted independently like rows, then copy them back
+            let width = width as usize;
+            let height = height as usize;
+            let columns = lines.start.min(width)..lines.end.min(width);
+            let column_len = height * 4;
+            let mut buffer = vec![0; columns.len() * column_len];
+            for y in 0..height {
+                for (i, x) in columns.clone().enumerate() {
+                    let idx = (y * width + x) * 4;
+                    buffer[i * column_len + y * 4..][..4].copy_from_slice(&data[idx..idx + 4]);
                 }
             }
-        } else {
-            // Horizontal sorting
+
+            tiles::for_each_chunk(&mut buffer, column_len, |i, column| {
+                sort_column(column, columns.start + i, width, options, threshold, min_segment);
+            });
+
             for y in 0..height {
-                let row_start = (y * width * 4) as usize;
-                let row_end = row_start + (width * 4) as usize;
-                let mut segments = Vec::new();
-                let mut start = row_start;
-                
-                // First, collect segments to sort
-                for x in (row_start..row_end).step_by(4) {
-                    let value = match options.channel {
-                        Some(0) => data[x],
-                        Some(1) => data[x + 1],
-                        Some(2) => data[x + 2],
-                        _ => ((data[x] as u16 + data[x + 1] as u16 + data[x + 2] as u16) / 3) as u8
-                    };
-                    
-                    if value > threshold || x + 4 >= row_end {
-                        if x > start && x - start >= min_segment * 4 {
-                            segments.push((start, x));
-                        }
-                        start = x + 4;
-                    }
-                }
-                
-                // Then sort each segment
-                for (start, end) in segments {
-                    let mut pixels: Vec<_> = (start..end)
-                        .step_by(4)
-                        .map(|i| [data[i], data[i + 1], data[i + 2], data[i + 3]])
-                        .collect();
-                    
-                    pixels.sort_by_key(|pixel| {
-                        match options.channel {
-                            Some(0) => pixel[0],
-                            Some(1) => pixel[1],
-                            Some(2) => pixel[2],
-                            _ => ((pixel[0] as u16 + pixel[1] as u16 + pixel[2] as u16) / 3) as u8
-                        }
-                    });
-                    
-                    // Write back sorted pixels
-                    for (idx, pixel) in pixels.iter().enumerate() {
-                        let pos = start + idx * 4;
-                        data[pos] = pixel[0];
-                        data[pos + 1] = pixel[1];
-                        data[pos + 2] = pixel[2];
-                        data[pos + 3] = pixel[3];
-                    }
+                for (i, x) in columns.clone().enumerate() {
+                    let idx = (y * width + x) * 4;
+                    data[idx..idx + 4].copy_from_slice(&buffer[i * column_len + y * 4..][..4]);
                 }
             }
+        } else {
+            // Horizontal sorting, each row independently
+            let row_len = width as usize * 4;
+            let start = (lines.start * row_len).min(data.len());
+            let end = (lines.end * row_len).min(data.len());
+            tiles::for_each_chunk(&mut data[start..end], row_len, |_, row| {
+                sort_row(row, options, threshold, min_segment);
+            });
         }
     }
 
-    fn data_bend_internal(&mut self, data: &mut [u8], options: &DataBendOptions) {
+    fn data_bend_internal(&mut self, data: &mut [u8], width: u32, options: &DataBendOptions, scale: f64) {
+        self.data_bend_iterations(data, width, options, Self::data_bend_total(options), scale);
+    }
+
+    fn data_bend_total(options: &DataBendOptions) -> usize {
+        (options.amount * 200.0) as usize
+    }
+
+    // Run some of a data bend's iterations. The random stream carries over between calls,
+    // so splitting the iterations up gives the same result as running them all at once.
+    fn data_bend_iterations(&mut self, data: &mut [u8], width: u32, options: &DataBendOptions, iterations: usize, scale: f64) {
         let len = data.len();
-        let iterations = (options.amount * 200.0) as usize;
-        let max_chunk_size = (options.chunk_size.unwrap_or(0.5) * 500.0) as usize;
-        let min_chunk = 16;
+        let max_chunk_size = (options.chunk_size.unwrap_or(0.5) * 500.0 * scale) as usize;
+        let min_chunk = ((16.0 * scale) as usize).max(8);
         
         for _ in 0..iterations {
+            // Every iteration draws the same values whatever the image size, and sizes and
+            // positions are fractions, so a smaller render makes the same decisions
+            let size_fraction: f64 = self.rng.gen();
+            let (x, y): (f64, f64) = (self.rng.gen(), self.rng.gen());
+            let shift_fraction: f64 = self.rng.gen();
+            let scramble_seed: u64 = self.rng.gen();
+            let mode = options.mode.unwrap_or_else(|| self.rng.gen_range(0..4));
+
             // Generate random chunk size
-            let chunk_size = self.rng.gen_range(min_chunk..max_chunk_size.max(min_chunk + 1));
+            let max_chunk = max_chunk_size.max(min_chunk + 1);
+            let chunk_size = min_chunk + ((max_chunk - min_chunk) as f64 * size_fraction) as usize;
             let chunk_size = chunk_size - (chunk_size % 4); // Ensure it's a multiple of 4
             
             // Generate random position ensuring we don't go out of bounds
             let max_start = len.saturating_sub(chunk_size * 2);
             if max_start < 4 { continue; }
             
-            let pos = self.rng.gen_range(0..max_start);
-            let pos = pos - (pos % 4); // Ensure we start at a pixel boundary
+            let pos = pixel_position(x, y, width as usize, len, max_start);
             
             // Determine which channels to affect
             let channels = match options.channel {
@@ -228,9 +513,6 @@ impl GlitchEffect {
                 _ => vec![0, 1, 2, 3], // All channels
             };
             
-            // Choose an effect type
-            let mode = options.mode.unwrap_or_else(|| self.rng.gen_range(0..4));
-            
             match mode {
                 0 => {
                     // Duplicate chunk
@@ -262,7 +544,7 @@ impl GlitchEffect {
                 }
                 2 => {
                     // Shift chunk
-                    let shift = self.rng.gen_range(4..chunk_size);
+                    let shift = 4 + ((chunk_size - 4) as f64 * shift_fraction) as usize;
                     let shift = shift - (shift % 4); // Ensure shift is multiple of 4
                     
                     let mut temp = vec![0; chunk_size];
@@ -287,13 +569,14 @@ impl GlitchEffect {
                         }
                     }
                 }
-                3 | _ => {
+                _ => {
                     // Scramble pixels within chunk
                     let mut pixel_indices: Vec<usize> = (0..chunk_size/4).collect();
                     
                     // Fisher-Yates shuffle
+                    let mut rng = SmallRng::seed_from_u64(scramble_seed);
                     for i in (1..pixel_indices.len()).rev() {
-                        let j = self.rng.gen_range(0..i+1);
+                        let j = rng.gen_range(0..i+1);
                         pixel_indices.swap(i, j);
                     }
                     
@@ -316,8 +599,8 @@ impl GlitchEffect {
         }
     }
 
-    fn channel_shift_internal(&mut self, data: &mut [u8], options: &ChannelShiftOptions) {
-        let shift_amount = (options.amount * 30.0) as i32;
+    fn channel_shift_internal(&mut self, data: &mut [u8], options: &ChannelShiftOptions, scale: f64) {
+        let shift_amount = (options.amount * 30.0 * scale) as i32;
         let len = data.len();
         
         let channels = match &options.channels {
@@ -326,7 +609,7 @@ impl GlitchEffect {
         };
         
         let direction = match options.direction {
-            Some(dir) if dir == 0 => if self.rng.gen_bool(0.5) { 1 } else { -1 },
+            Some(0) => if self.rng.gen_bool(0.5) { 1 } else { -1 },
             Some(dir) => dir,
             None => if self.rng.gen_bool(0.5) { 1 } else { -1 }
         };
@@ -353,96 +636,74 @@ impl GlitchEffect {
     
     fn add_noise_internal(&mut self, data: &mut [u8], amount: f64) {
         let noise_amount = (amount * 255.0) as u8;
+        if noise_amount == 0 { return; }
         
-        for i in (0..data.len()).step_by(4) {
-            // Skip alpha channel
-            for j in 0..3 {
-                let noise = self.rng.gen_range(0..noise_amount);
-                let add_noise = self.rng.gen_bool(0.5);
-                
-                if add_noise {
-                    data[i + j] = data[i + j].saturating_add(noise);
-                } else {
-                    data[i + j] = data[i + j].saturating_sub(noise);
-                }
-            }
-        }
+        // Each tile gets its own stream so the result doesn't depend on how tiles are scheduled
+        let seed: u64 = self.rng.gen();
+        tiles::for_each_chunk(data, tiles::TILE_PIXELS * 4, |index, tile| {
+            let mut rng = tiles::chunk_rng(seed, index);
+            let deltas = kernels::noise_deltas(&mut rng, tile.len(), noise_amount);
+            kernels::noise(tile, &deltas);
+        });
     }
     
     fn invert_channels_internal(&self, data: &mut [u8], channels: &[usize]) {
-        for i in (0..data.len()).step_by(4) {
-            for &ch in channels {
-                if ch < 4 {
-                    data[i + ch] = 255 - data[i + ch];
-                }
-            }
-        }
+        let mask = kernels::invert_mask(channels);
+        tiles::for_each_chunk(data, tiles::TILE_PIXELS * 4, |_, tile| kernels::invert(tile, mask));
     }
     
     fn quantize_internal(&self, data: &mut [u8], levels: usize) {
         if levels <= 1 { return; }
         
-        let divisor = 255.0 / (levels - 1) as f64;
-        
-        for i in (0..data.len()).step_by(4) {
-            // Skip alpha channel
-            for j in 0..3 {
-                let value = data[i + j] as f64;
-                let quantized = (((value / divisor).round() * divisor) as u8).min(255);
-                data[i + j] = quantized;
-            }
-        }
+        let table = kernels::quantize_table(levels);
+        tiles::for_each_chunk(data, tiles::TILE_PIXELS * 4, |_, tile| kernels::quantize(tile, &table));
     }
 
-    fn byte_corrupt_internal(&mut self, data: &mut [u8], options: &ByteCorruptOptions) {
+    fn byte_corrupt_internal(&mut self, data: &mut [u8], width: u32, options: &ByteCorruptOptions, scale: f64) {
         let len = data.len();
+        if len == 0 { return; }
         let corruption_intensity = (options.amount * 0.1).min(0.05); // Cap to avoid completely destroying the image
-        let num_corruptions = (len as f64 * corruption_intensity) as usize;
-        let block_size = options.block_size.unwrap_or(1).max(1);
+        // The number of corruptions is fixed by the image area in reference pixels, so every
+        // resolution gets the same ones; their size follows the real area instead
+        let area = scale * scale;
+        let num_corruptions = (len as f64 / area * corruption_intensity) as usize;
+        let exact_block = options.block_size.unwrap_or(1).max(1) as f64 * area;
+        let block_size = (exact_block.round() as usize).max(1);
+        // Blocks smaller than a byte happen with a matching probability instead
+        let chance = exact_block.min(1.0);
+        let width = width as usize;
+        
+        // Each corruption has its own random stream, so a smaller render gets the same
+        // decisions for the corruptions it shares with a larger one
+        let seed: u64 = self.rng.gen();
         
         if options.structured {
-            // Create structured corruption patterns
-            let stride = (len as f64 / num_corruptions as f64).max(1.0) as usize;
+            // Create structured corruption patterns: a regular grid over the picture
             let mode = options.mode.unwrap_or_else(|| self.rng.gen_range(0..4));
+            let columns = (num_corruptions as f64).sqrt().ceil().max(1.0) as usize;
+            let rows = num_corruptions.div_ceil(columns).max(1);
             
-            // Fix: clone the range to avoid borrowing issues
-            let positions: Vec<usize> = (0..num_corruptions)
-                .filter(|_| {
-                    // Generate random boolean outside of closure to avoid borrow issues
-                    self.rng.gen_bool(0.7)
-                })
-                .map(|i| (i * stride) % len) // Calculate positions up front
-                .collect();
-            
-            for start_pos in positions {
-                let end_pos = (start_pos + block_size).min(len);
+            for i in 0..num_corruptions {
+                let mut rng = tiles::chunk_rng(seed, i);
+                if !rng.gen_bool(0.7) || !rng.gen_bool(chance) { continue; }
                 
-                for pos in start_pos..end_pos {
-                    match mode {
-                        0 => data[pos] = self.rng.gen(), // Random bytes
-                        1 => data[pos] ^= 1 << self.rng.gen_range(0..8), // Bit flip
-                        2 => data[pos] = 0, // Zero out
-                        3 => data[pos] = 255, // Max out
-                        _ => data[pos] = self.rng.gen(), // Default to random
-                    }
-                }
+                let x = ((i % columns) as f64 + 0.5) / columns as f64;
+                let y = ((i / columns) as f64 + 0.5) / rows as f64;
+                let start_pos = pixel_position(x, y, width, len, len - 1);
+                let end_pos = (start_pos + block_size).min(len);
+                corrupt_bytes(&mut data[start_pos..end_pos], mode, &mut rng);
             }
         } else {
-            // Random corruption
-            for _ in 0..num_corruptions {
-                let pos = self.rng.gen_range(0..len);
+            // Random corruption at normalized positions
+            for i in 0..num_corruptions {
+                let mut rng = tiles::chunk_rng(seed, i);
+                let (x, y): (f64, f64) = (rng.gen(), rng.gen());
+                let channel = rng.gen_range(0..4);
+                let mode = options.mode.unwrap_or_else(|| rng.gen_range(0..4));
+                if !rng.gen_bool(chance) { continue; }
+                let pos = (pixel_position(x, y, width, len, len - 1) + channel).min(len - 1);
                 let end_pos = (pos + block_size).min(len);
-                let mode = options.mode.unwrap_or_else(|| self.rng.gen_range(0..4));
-                
-                for p in pos..end_pos {
-                    match mode {
-                        0 => data[p] = self.rng.gen(), // Random bytes
-                        1 => data[p] ^= 1 << self.rng.gen_range(0..8), // Bit flip
-                        2 => data[p] = 0, // Zero out
-                        3 => data[p] = 255, // Max out
-                        _ => data[p] = self.rng.gen(), // Default to random
-                    }
-                }
+                corrupt_bytes(&mut data[pos..end_pos], mode, &mut rng);
             }
         }
     }
@@ -452,7 +713,7 @@ impl GlitchEffect {
         let height = (len / 4) as u32 / width;
         
         // Fix: Convert height to usize first, then calculate base chunk size
-        let base_chunk_size = (width as usize * (height as usize) / 20); // Base chunk is 5% of image
+        let base_chunk_size = width as usize * (height as usize) / 20; // Base chunk is 5% of image
         let chunk_size_factor = options.chunk_size.unwrap_or(0.5);
         let chunk_size = (base_chunk_size as f64 * chunk_size_factor) as usize;
         let chunk_size = chunk_size.max(16).min(len / 8); // Reasonable bounds
@@ -460,15 +721,17 @@ impl GlitchEffect {
         let num_swaps = (options.amount * 10.0) as usize;
         
         for _ in 0..num_swaps {
+            // Positions are drawn as fractions of the width and height so they land in the
+            // same place at any resolution
+            let (x1, y1): (f64, f64) = (self.rng.gen(), self.rng.gen());
+            let (x2, y2): (f64, f64) = (self.rng.gen(), self.rng.gen());
+            
             // Ensure we don't go out of bounds and chunks are pixel-aligned
             let max_start = len.saturating_sub(chunk_size * 2);
             if max_start < 4 { continue; }
             
-            let pos1 = self.rng.gen_range(0..max_start);
-            let pos1 = pos1 - (pos1 % 4); // Ensure pixel alignment
-            
-            let pos2 = self.rng.gen_range(0..max_start);
-            let pos2 = pos2 - (pos2 % 4); // Ensure pixel alignment
+            let pos1 = pixel_position(x1, y1, width as usize, len, max_start);
+            let pos2 = pixel_position(x2, y2, width as usize, len, max_start);
             
             // Swap chunks
             for i in 0..chunk_size {
@@ -505,167 +768,317 @@ impl GlitchEffect {
         
         let mode = options.mode.unwrap_or(0);
         let strength = (options.strength * 255.0) as u8;
+        let xor_value = |pattern_byte: u8| ((pattern_byte as u16 * strength as u16) / 255) as u8;
         
         match mode {
             0 => {
                 // Full image XOR
-                for (i, byte) in data.iter_mut().enumerate() {
-                    let pattern_byte = pattern[i % pattern.len()];
-                    let xor_value = ((pattern_byte as u16 * strength as u16) / 255) as u8;
-                    *byte ^= xor_value;
-                }
-            },
-            1 => {
-                // Horizontal bands
-                let band_height = height / pattern.len().max(1);
-                for y in 0..height {
-                    let band = y / band_height;
-                    let pattern_byte = pattern[band % pattern.len()];
-                    let xor_value = ((pattern_byte as u16 * strength as u16) / 255) as u8;
-                    
-                    let row_start = (y * width as usize * 4) as usize;
-                    let row_end = row_start + (width as usize * 4);
-                    
-                    for i in (row_start..row_end).step_by(4) {
-                        // Apply to RGB but not alpha
-                        for j in 0..3 {
-                            if i + j < len {
-                                data[i + j] ^= xor_value;
-                            }
-                        }
-                    }
-                }
-            },
-            2 => {
-                // Vertical bands
-                let band_width = width / pattern.len().max(1) as u32;
-                for x in 0..width {
-                    let band = x / band_width;
-                    let pattern_byte = pattern[band as usize % pattern.len()];
-                    let xor_value = ((pattern_byte as u16 * strength as u16) / 255) as u8;
-                    
-                    for y in 0..height {
-                        let i = (y * width as usize * 4 + x as usize * 4) as usize;
-                        // Apply to RGB but not alpha
-                        for j in 0..3 {
-                            if i + j < len {
-                                data[i + j] ^= xor_value;
-                            }
-                        }
-                    }
-                }
+                let tile_len = tiles::TILE_PIXELS * 4;
+                let values: Vec<u8> = pattern.iter().map(|&p| xor_value(p)).collect();
+                tiles::for_each_chunk(data, tile_len, |index, tile| {
+                    kernels::xor_pattern(tile, &values, index * tile_len % values.len());
+                });
             },
-            3 => {
-                // Blocks
-                let block_width = width / 8;
-                let block_height = height as u32 / 8;
+            1..=3 => {
+                // Horizontal bands, vertical bands or blocks, processed row by row
+                let width = width as usize;
+                let band_height = (height / pattern.len()).max(1);
+                let band_width = (width / pattern.len()).max(1);
+                let block_width = (width / 8).max(1);
+                let block_height = (height / 8).max(1);
                 
-                for y in 0..height as u32 {
-                    let block_y = y / block_height;
-                    for x in 0..width {
-                        let block_x = x / block_width;
-                        let block_idx = (block_y * 8 + block_x) as usize;
-                        let pattern_byte = pattern[block_idx % pattern.len()];
-                        let xor_value = ((pattern_byte as u16 * strength as u16) / 255) as u8;
-                        
-                        let i = (y as usize * width as usize * 4 + x as usize * 4) as usize;
-                        // Apply to RGB but not alpha
-                        for j in 0..3 {
-                            if i + j < len {
-                                data[i + j] ^= xor_value;
-                            }
-                        }
+                tiles::for_each_chunk(data, width * 4, |y, row| {
+                    // Build the row's XOR mask, then apply it in one pass (RGB but not alpha)
+                    let mut mask = vec![0u8; row.len()];
+                    for (x, pixel) in mask.chunks_exact_mut(4).enumerate() {
+                        let pattern_idx = match mode {
+                            1 => y / band_height,
+                            2 => x / band_width,
+                            _ => (y / block_height) * 8 + x / block_width,
+                        };
+                        pixel[..3].fill(xor_value(pattern[pattern_idx % pattern.len()]));
                     }
-                }
+                    kernels::xor_bytes(row, &mask);
+                });
             },
             _ => {} // Invalid mode, do nothing
         }
     }
 
-    fn image_blend_internal(&mut self, data: &mut [u8], width: u32, options: &ImageBlendOptions) {
-        let primary_height = data.len() / 4 / width as usize;
-        let secondary_width = options.width;
-        let secondary_height = options.height;
-        
-        // Check if the secondary image data is valid
-        if options.secondary_data.len() != (secondary_width * secondary_height * 4) as usize {
-            return; // Invalid secondary image data
+    fn image_blend_internal(&self, data: &mut [u8], width: u32, secondary: &SecondaryImage, compositing: &Compositing, amount: f64) {
+        // Source-over in gamma space on opaque pixels has fixed-point kernels for the first five
+        // modes. They only round exactly like the compositor at full strength, where the output
+        // is the blended value itself, so mixed rows can't band against each other.
+        let fast = amount >= 1.0 && compositing.mode <= 4 && compositing.operator == 0 && !compositing.linear;
+
+        // Rows are independent, so they can be blended in parallel
+        tiles::for_each_chunk(data, width as usize * 4, |y, row| {
+            // Gather the secondary row (with offset and wrap-around), then blend it in one pass
+            let mut secondary_row = vec![0u8; row.len()];
+            for (x, pixel) in secondary_row.chunks_exact_mut(4).enumerate() {
+                pixel.copy_from_slice(&secondary.pixel(x, y));
+            }
+            let opaque = |row: &[u8]| row.chunks_exact(4).all(|pixel| pixel[3] == 255);
+            if fast && opaque(row) && opaque(&secondary_row) {
+                kernels::blend(row, &secondary_row, compositing.mode, kernels::blend_amount(1.0));
+            } else {
+                blend::composite_row(row, &secondary_row, compositing, amount.clamp(0.0, 1.0) as f32);
+            }
+        });
+    }
+
+    fn yuv_internal(&mut self, data: &mut [u8], width: u32, options: &YuvOptions, scale: f64) {
+        let plane_width = width as usize;
+        if plane_width == 0 { return; }
+        let mut planes = channels::split(data, 2);
+
+        // Route effect chains to individual planes
+        for plane_effects in options.planes.iter().flatten() {
+            if let Some(plane) = planes[..3].get_mut(plane_effects.plane) {
+                let mut view = channels::plane_view(plane);
+                self.process_internal(&mut view, width, &plane_effects.effects);
+                channels::plane_from_view(&view, plane);
+            }
         }
-        
-        let amount = options.amount;
-        let inverse_amount = 1.0 - amount;
-        
-        for y in 0..primary_height {
-            for x in 0..width as usize {
-                let primary_idx = (y * width as usize + x) * 4;
-                
-                // Calculate position in secondary image with offset
-                let sec_x = (x as i32 + options.offset_x).rem_euclid(secondary_width as i32) as usize;
-                let sec_y = (y as i32 + options.offset_y).rem_euclid(secondary_height as i32) as usize;
-                let secondary_idx = (sec_y * secondary_width as usize + sec_x) * 4;
-                
-                if primary_idx + 3 < data.len() && secondary_idx + 3 < options.secondary_data.len() {
-                    // Get pixel values
-                    let p_r = data[primary_idx];
-                    let p_g = data[primary_idx + 1];
-                    let p_b = data[primary_idx + 2];
-                    
-                    let s_r = options.secondary_data[secondary_idx];
-                    let s_g = options.secondary_data[secondary_idx + 1];
-                    let s_b = options.secondary_data[secondary_idx + 2];
-                    
-                    // Apply blend based on mode
-                    match options.blend_mode {
-                        0 => {
-                            // Mix blend (linear interpolation)
-                            data[primary_idx] = ((p_r as f64 * inverse_amount) + (s_r as f64 * amount)) as u8;
-                            data[primary_idx + 1] = ((p_g as f64 * inverse_amount) + (s_g as f64 * amount)) as u8;
-                            data[primary_idx + 2] = ((p_b as f64 * inverse_amount) + (s_b as f64 * amount)) as u8;
-                        },
-                        1 => {
-                            // Difference blend
-                            data[primary_idx] = ((p_r as i16 - s_r as i16).abs() as f64 * amount + p_r as f64 * inverse_amount) as u8;
-                            data[primary_idx + 1] = ((p_g as i16 - s_g as i16).abs() as f64 * amount + p_g as f64 * inverse_amount) as u8;
-                            data[primary_idx + 2] = ((p_b as i16 - s_b as i16).abs() as f64 * amount + p_b as f64 * inverse_amount) as u8;
-                        },
-                        2 => {
-                            // Multiply blend
-                            data[primary_idx] = ((p_r as f64 * s_r as f64 / 255.0) * amount + p_r as f64 * inverse_amount) as u8;
-                            data[primary_idx + 1] = ((p_g as f64 * s_g as f64 / 255.0) * amount + p_g as f64 * inverse_amount) as u8;
-                            data[primary_idx + 2] = ((p_b as f64 * s_b as f64 / 255.0) * amount + p_b as f64 * inverse_amount) as u8;
-                        },
-                        3 => {
-                            // Screen blend
-                            let screen_r = 255 - ((255 - p_r as u16) * (255 - s_r as u16) / 255) as u8;
-                            let screen_g = 255 - ((255 - p_g as u16) * (255 - s_g as u16) / 255) as u8;
-                            let screen_b = 255 - ((255 - p_b as u16) * (255 - s_b as u16) / 255) as u8;
-                            
-                            data[primary_idx] = (screen_r as f64 * amount + p_r as f64 * inverse_amount) as u8;
-                            data[primary_idx + 1] = (screen_g as f64 * amount + p_g as f64 * inverse_amount) as u8;
-                            data[primary_idx + 2] = (screen_b as f64 * amount + p_b as f64 * inverse_amount) as u8;
-                        },
-                        4 => {
-                            // Overlay blend
-                            let overlay = |a: u8, b: u8| -> u8 {
-                                if a < 128 {
-                                    ((2 * a as u16 * b as u16) / 255) as u8
-                                } else {
-                                    (255 - 2 * (255 - a as u16) * (255 - b as u16) / 255) as u8
-                                }
-                            };
-                            
-                            let o_r = overlay(p_r, s_r);
-                            let o_g = overlay(p_g, s_g);
-                            let o_b = overlay(p_b, s_b);
-                            
-                            data[primary_idx] = (o_r as f64 * amount + p_r as f64 * inverse_amount) as u8;
-                            data[primary_idx + 1] = (o_g as f64 * amount + p_g as f64 * inverse_amount) as u8;
-                            data[primary_idx + 2] = (o_b as f64 * amount + p_b as f64 * inverse_amount) as u8;
-                        },
-                        _ => {} // Invalid mode, do nothing
+
+        // Sort by luma while leaving the chroma in place
+        if let Some(luma_sort) = &options.luma_sort {
+            let mut view = channels::plane_view(&planes[0]);
+            self.pixel_sort_internal(&mut view, width, luma_sort, scale);
+            channels::plane_from_view(&view, &mut planes[0]);
+        }
+
+        // Chroma subsampling with nearest upsampling
+        let block = match options.subsampling {
+            Some(1) => Some((2, 1)), // 4:2:2
+            Some(2) => Some((2, 2)), // 4:2:0
+            Some(3) => Some((4, 1)), // 4:1:1
+            _ => None,
+        };
+        if let Some((block_width, block_height)) = block {
+            for plane in &mut planes[1..3] {
+                yuv::subsample(plane, plane_width, block_width, block_height);
+            }
+        }
+
+        // Move the chroma independently of the luma
+        let dx = scale_offset(options.chroma_shift_x.unwrap_or(0), scale);
+        let dy = scale_offset(options.chroma_shift_y.unwrap_or(0), scale);
+        if dx != 0 || dy != 0 {
+            for plane in &mut planes[1..3] {
+                yuv::shift_plane(plane, plane_width, dx, dy);
+            }
+        }
+
+        channels::merge(data, &planes, 2);
+    }
+
+    // Run a channel's effects on a grayscale view of it, in the order of `ChannelEffects`
+    fn channel_effects_internal(&mut self, view: &mut [u8], width: u32, effects: &ChannelEffects, scale: f64) {
+        if let Some(pixel_sort_options) = &effects.pixel_sort {
+            self.pixel_sort_internal(view, width, pixel_sort_options, scale);
+        }
+        if let Some(data_bend_options) = &effects.data_bend {
+            self.data_bend_internal(view, width, data_bend_options, scale);
+        }
+        if let Some(shift_options) = &effects.shift {
+            // All RGB components hold the same plane, so shift them together
+            let options = ChannelShiftOptions {
+                amount: shift_options.amount,
+                channels: Some(vec![0, 1, 2]),
+                direction: shift_options.direction,
+            };
+            self.channel_shift_internal(view, &options, scale);
+        }
+        if let Some(amount) = effects.noise {
+            self.add_noise_internal(view, amount);
+        }
+        if effects.invert == Some(true) {
+            self.invert_channels_internal(view, &[0, 1, 2]);
+        }
+        if let Some(levels) = effects.quantize {
+            self.quantize_internal(view, levels);
+        }
+        if let Some(byte_corrupt_options) = &effects.byte_corrupt {
+            self.byte_corrupt_internal(view, width, byte_corrupt_options, scale);
+        }
+        if let Some(binary_xor_options) = &effects.binary_xor {
+            self.binary_xor_internal(view, width, binary_xor_options);
+        }
+    }
+
+    // Split into planes once, run each chain on its plane and recombine. Chains in the
+    // same color space see the planes as they were before any chain ran.
+    fn channel_chains_internal(&mut self, data: &mut [u8], width: u32, chains: &[(usize, usize, &ChannelEffects)], scale: f64) {
+        for space in 0..3 {
+            let mut chains_in_space = chains.iter().filter(|(s, _, _)| *s == space).peekable();
+            if chains_in_space.peek().is_none() { continue; }
+
+            let mut planes = channels::split(data, space);
+            for &(_, channel, effects) in chains_in_space {
+                let max_channel = if space == 0 { 4 } else { 3 };
+                if channel >= max_channel { continue; }
+                let mut view = channels::plane_view(&planes[channel]);
+                self.channel_effects_internal(&mut view, width, effects, scale);
+                channels::plane_from_view(&view, &mut planes[channel]);
+            }
+            channels::merge(data, &planes, space);
+        }
+    }
+
+    // Run every enabled effect in pipeline order
+    fn process_internal(&mut self, data: &mut [u8], width: u32, options: &GlitchOptions) {
+        let maps = masks::Maps::new(data, width, options);
+        for stage in job::stages(options) {
+            self.process_stage(data, width, options, stage, &maps);
+        }
+    }
+
+    // Run one whole stage, limited by its masks. Returns the seed the random stream continues
+    // from afterwards, which is all a cached result needs to resume the pipeline.
+    fn process_stage(&mut self, data: &mut [u8], width: u32, options: &GlitchOptions, stage: Stage, maps: &masks::Maps) -> u64 {
+        let next_seed = self.begin_stage();
+        let units = Self::stage_units(stage, data.len(), width, options);
+        let threshold = Self::stage_threshold(stage, data, width, options);
+        let mask = maps.weights(options, stage).map(|weights| (weights, data.to_vec()));
+        self.run_stage(stage, data, width, options, 0..units, threshold);
+        if let Some((weights, before)) = mask {
+            masks::blend(data, &before, &weights);
+        }
+        self.end_stage(next_seed);
+        next_seed
+    }
+
+    // Draw the random stream for the stages after this one up front, so a stage that uses
+    // more random numbers on a larger image doesn't change what later stages do. This keeps
+    // proxy renders and full-resolution exports in step.
+    fn begin_stage(&mut self) -> u64 {
+        self.rng.gen()
+    }
+
+    fn end_stage(&mut self, next_seed: u64) {
+        self.rng = SmallRng::seed_from_u64(next_seed);
+    }
+
+    // Units a stage can be split into for incremental rendering: rows or columns for pixel
+    // sort, iterations for data bend and a single unit for everything else
+    fn stage_units(stage: Stage, len: usize, width: u32, options: &GlitchOptions) -> usize {
+        match stage {
+            Stage::PixelSort => options.pixel_sort.as_ref().map_or(1, |o| Self::pixel_sort_lines(len, width, o)),
+            Stage::DataBend => options.data_bend.as_ref().map_or(1, Self::data_bend_total),
+            _ => 1,
+        }
+    }
+
+    // Pixel sort threshold for a stage, resolved from the stage's input before it starts.
+    // Sorting only moves pixels within a line, so an automatic threshold is the same for
+    // every part of a split sort.
+    fn stage_threshold(stage: Stage, data: &[u8], width: u32, options: &GlitchOptions) -> Option<u8> {
+        let pixel_sort_options = options.pixel_sort.as_ref().filter(|_| stage == Stage::PixelSort)?;
+        let scale = spatial_scale(data.len(), options.absolute_units.unwrap_or(false));
+        Some(resolve_threshold(data, width, pixel_sort_options, min_segment(pixel_sort_options, scale)))
+    }
+
+    // Run part of one pipeline stage. `units` only matters for stages that can be split, and
+    // `threshold` is the stage's `stage_threshold`.
+    fn run_stage(&mut self, stage: Stage, data: &mut [u8], width: u32, options: &GlitchOptions, units: Range<usize>,
+                 threshold: Option<u8>) {
+        let scale = spatial_scale(data.len(), options.absolute_units.unwrap_or(false));
+        match stage {
+            Stage::PixelSort => {
+                if let (Some(pixel_sort_options), Some(threshold)) = (&options.pixel_sort, threshold) {
+                    self.pixel_sort_range(data, width, pixel_sort_options, threshold, units, scale);
+                }
+            }
+            Stage::DataBend => {
+                if let Some(data_bend_options) = &options.data_bend {
+                    self.data_bend_iterations(data, width, data_bend_options, units.len(), scale);
+                }
+            }
+            Stage::ChannelShift => {
+                if let Some(channel_shift_options) = &options.channel_shift {
+                    self.channel_shift_internal(data, channel_shift_options, scale);
+                }
+            }
+            Stage::Noise => {
+                if let Some(amount) = options.noise {
+                    self.add_noise_internal(data, amount);
+                }
+            }
+            Stage::Invert => {
+                if let Some(channels) = &options.invert {
+                    self.invert_channels_internal(data, channels);
+                }
+            }
+            Stage::Quantize => {
+                if let Some(levels) = options.quantize {
+                    self.quantize_internal(data, levels);
+                }
+            }
+            Stage::ByteCorrupt => {
+                if let Some(byte_corrupt_options) = &options.byte_corrupt {
+                    self.byte_corrupt_internal(data, width, byte_corrupt_options, scale);
+                }
+            }
+            Stage::ChunkSwap => {
+                if let Some(chunk_swap_options) = &options.chunk_swap {
+                    self.chunk_swap_internal(data, width, chunk_swap_options);
+                }
+            }
+            Stage::BinaryXor => {
+                if let Some(binary_xor_options) = &options.binary_xor {
+                    self.binary_xor_internal(data, width, binary_xor_options);
+                }
+            }
+            Stage::ImageBlend => {
+                if let Some(image_blend_options) = &options.image_blend {
+                    if let Some(secondary) = image_blend_options.secondary(&self.images, width, data.len(), scale) {
+                        self.image_blend_internal(data, width, &secondary, &image_blend_options.compositing(), image_blend_options.amount);
                     }
                 }
             }
+            Stage::Sonify => {
+                if let Some(sonify_options) = &options.sonify {
+                    sonify::sonify(data, width, sonify_options, scale);
+                }
+            }
+            Stage::Fourier => {
+                if let Some(fourier_options) = &options.fourier {
+                    let secondary = fourier_options.secondary(&self.images);
+                    fourier::fourier(data, width, fourier_options, secondary.as_ref(), scale, &mut self.rng);
+                }
+            }
+            Stage::BitPlane => {
+                if let Some(bit_plane_options) = &options.bit_plane {
+                    bitplane::bit_plane(data, width, bit_plane_options, &mut self.rng);
+                }
+            }
+            Stage::Reinterpret => {
+                if let Some(reinterpret_options) = &options.reinterpret {
+                    reinterpret::reinterpret(data, width, reinterpret_options, scale);
+                }
+            }
+            Stage::Yuv => {
+                if let Some(yuv_options) = &options.yuv {
+                    self.yuv_internal(data, width, yuv_options, scale);
+                }
+            }
+            Stage::ChannelChains => {
+                let chains = job::channel_chains(options);
+                if !chains.is_empty() {
+                    self.channel_chains_internal(data, width, &chains, scale);
+                }
+            }
+            Stage::Layers => {
+                if let Some(layers) = &options.layers {
+                    layers::composite(self, data, width, layers, scale);
+                }
+            }
+            Stage::Transform => {
+                if let Some(transform_options) = &options.transform {
+                    transform::transform(data, width, transform_options, scale);
+                }
+            }
         }
     }
 
@@ -677,19 +1090,24 @@ impl GlitchEffect {
             threshold,
             vertical,
             channel,
+            auto_threshold: None,
+            auto_target: None,
         };
-        self.pixel_sort_internal(data, width, &options);
+        self.pixel_sort_internal(data, width, &options, spatial_scale(data.len(), false));
     }
     
+    // Without a width the buffer counts as a single row
     #[wasm_bindgen]
-    pub fn data_bend(&mut self, data: &mut [u8], amount: f64, mode: Option<usize>, chunk_size: Option<f64>, channel: Option<usize>) {
+    pub fn data_bend(&mut self, data: &mut [u8], amount: f64, mode: Option<usize>, chunk_size: Option<f64>, channel: Option<usize>,
+                     width: Option<u32>) {
         let options = DataBendOptions {
             amount,
             mode,
             chunk_size,
             channel,
         };
-        self.data_bend_internal(data, &options);
+        let width = width.unwrap_or((data.len() / 4) as u32);
+        self.data_bend_internal(data, width, &options, spatial_scale(data.len(), false));
     }
     
     #[wasm_bindgen]
@@ -700,7 +1118,7 @@ impl GlitchEffect {
             channels: channels_vec,
             direction,
         };
-        self.channel_shift_internal(data, &options);
+        self.channel_shift_internal(data, &options, spatial_scale(data.len(), false));
     }
     
     #[wasm_bindgen]
@@ -718,15 +1136,18 @@ impl GlitchEffect {
         self.quantize_internal(data, levels);
     }
 
+    // Without a width the buffer counts as a single row
     #[wasm_bindgen]
-    pub fn byte_corrupt(&mut self, data: &mut [u8], amount: f64, mode: Option<usize>, block_size: Option<usize>, structured: bool) {
+    pub fn byte_corrupt(&mut self, data: &mut [u8], amount: f64, mode: Option<usize>, block_size: Option<usize>, structured: bool,
+                        width: Option<u32>) {
         let options = ByteCorruptOptions {
             amount,
             mode,
             block_size,
             structured,
         };
-        self.byte_corrupt_internal(data, &options);
+        let width = width.unwrap_or((data.len() / 4) as u32);
+        self.byte_corrupt_internal(data, width, &options, spatial_scale(data.len(), false));
     }
 
     #[wasm_bindgen]
@@ -751,19 +1172,206 @@ impl GlitchEffect {
     }
 
     #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
     pub fn image_blend(&mut self, data: &mut [u8], width: u32, secondary_data: &[u8], 
                         secondary_width: u32, secondary_height: u32,
                         blend_mode: usize, amount: f64, offset_x: i32, offset_y: i32) {
-        let options = ImageBlendOptions {
-            secondary_data: secondary_data.to_vec(),
-            width: secondary_width,
-            height: secondary_height,
-            blend_mode,
-            amount,
-            offset_x,
-            offset_y,
+        let scale = spatial_scale(data.len(), false);
+        let (offset_x, offset_y) = (scale_offset(offset_x, scale), scale_offset(offset_y, scale));
+        if let Some(secondary) = SecondaryImage::new(secondary_data, secondary_width, secondary_height, offset_x, offset_y) {
+            self.image_blend_internal(data, width, &secondary, &Compositing { mode: blend_mode, operator: 0, linear: false }, amount);
+        }
+    }
+
+    // Image blend against a secondary image registered with `register_image`
+    #[wasm_bindgen]
+    #[allow(clippy::too_many_arguments)]
+    pub fn image_blend_registered(&mut self, data: &mut [u8], width: u32, handle: u32,
+                                  blend_mode: usize, amount: f64, offset_x: i32, offset_y: i32) -> Result<(), JsValue> {
+        let scale = spatial_scale(data.len(), false);
+        let secondary = self.images.get(handle, scale_offset(offset_x, scale), scale_offset(offset_y, scale))
+            .ok_or_else(|| JsValue::from_str("Unknown secondary image handle"))?;
+        self.image_blend_internal(data, width, &secondary, &Compositing { mode: blend_mode, operator: 0, linear: false }, amount);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn sonify(&mut self, data: &mut [u8], width: u32, options_js: JsValue) -> Result<(), JsValue> {
+        let options: SonifyOptions = serde_wasm_bindgen::from_value(options_js)?;
+        sonify::sonify(data, width, &options, spatial_scale(data.len(), false));
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn transform(&mut self, data: &mut [u8], width: u32, options_js: JsValue) -> Result<(), JsValue> {
+        let options: TransformOptions = serde_wasm_bindgen::from_value(options_js)?;
+        transform::transform(data, width, &options, spatial_scale(data.len(), false));
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn fourier(&mut self, data: &mut [u8], width: u32, options_js: JsValue,
+                   secondary_data: Option<Box<[u8]>>, secondary_width: u32, secondary_height: u32) -> Result<(), JsValue> {
+        let options: FourierOptions = serde_wasm_bindgen::from_value(options_js)?;
+        let secondary = secondary_data.as_deref()
+            .and_then(|secondary_data| SecondaryImage::new(secondary_data, secondary_width, secondary_height, 0, 0));
+        fourier::fourier(data, width, &options, secondary.as_ref(), spatial_scale(data.len(), false), &mut self.rng);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn bit_plane(&mut self, data: &mut [u8], width: u32, options_js: JsValue) -> Result<(), JsValue> {
+        let options: BitPlaneOptions = serde_wasm_bindgen::from_value(options_js)?;
+        bitplane::bit_plane(data, width, &options, &mut self.rng);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn reinterpret(&mut self, data: &mut [u8], width: u32, format: usize, width_offset: Option<i32>, byte_offset: Option<usize>) {
+        let options = ReinterpretOptions {
+            format,
+            width_offset,
+            byte_offset,
+        };
+        reinterpret::reinterpret(data, width, &options, spatial_scale(data.len(), false));
+    }
+
+    #[wasm_bindgen]
+    pub fn yuv(&mut self, data: &mut [u8], width: u32, options_js: JsValue) -> Result<(), JsValue> {
+        let options: YuvOptions = serde_wasm_bindgen::from_value(options_js)?;
+        self.yuv_internal(data, width, &options, spatial_scale(data.len(), false));
+        Ok(())
+    }
+
+    // Threshold (0.0-1.0) the most recent pixel sort used, e.g. the value an automatic mode
+    // picked, so the UI can show it as a starting point for manual tuning
+    #[wasm_bindgen]
+    pub fn last_sort_threshold(&self) -> Option<f64> {
+        self.sort_threshold.map(|threshold| threshold as f64 / 255.0)
+    }
+
+    // Threshold (0.0-1.0) pixel sort options would use on this image, without sorting
+    #[wasm_bindgen]
+    pub fn pixel_sort_threshold(&self, data: &[u8], width: u32, options_js: JsValue) -> Result<f64, JsValue> {
+        let options: PixelSortOptions = serde_wasm_bindgen::from_value(options_js)?;
+        let min_segment = min_segment(&options, spatial_scale(data.len(), false));
+        Ok(resolve_threshold(data, width, &options, min_segment) as f64 / 255.0)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SmallRng::seed_from_u64(seed);
+    }
+
+    // Returns the options with all modulation routes applied at the given time
+    #[wasm_bindgen]
+    pub fn modulate_options(&self, options_js: JsValue, time: f64) -> Result<JsValue, JsValue> {
+        let mut options: GlitchOptions = serde_wasm_bindgen::from_value(options_js)?;
+        modulation::apply_modulation(&mut options, time);
+        audio::apply_audio(&mut options);
+        Ok(serde_wasm_bindgen::to_value(&options)?)
+    }
+
+    #[wasm_bindgen]
+    pub fn evaluate_modulator(&self, modulator_js: JsValue, time: f64, seed: u64) -> Result<f64, JsValue> {
+        let modulator: modulation::ModulatorOptions = serde_wasm_bindgen::from_value(modulator_js)?;
+        Ok(modulation::evaluate(&modulator, time, seed))
+    }
+
+    // Register a secondary image once and get a handle for `image_blend.secondary_image`
+    #[wasm_bindgen]
+    pub fn register_image(&mut self, data: &[u8], width: u32, height: u32) -> Result<u32, JsValue> {
+        SecondaryImage::new(data, width, height, 0, 0)
+            .ok_or_else(|| JsValue::from_str("Secondary image data does not match its dimensions"))?;
+        Ok(self.images.insert(data.to_vec(), width, height))
+    }
+
+    // Content hash of a registered image, as used for recipe assets
+    #[wasm_bindgen]
+    pub fn image_hash(&self, handle: u32) -> Option<String> {
+        self.images.hash(handle).map(str::to_string)
+    }
+
+    // Replace the pixels behind an existing handle, e.g. for a video used as secondary image.
+    // Cheap enough per frame: the content hash is only recomputed when something asks for it.
+    #[wasm_bindgen]
+    pub fn update_image(&mut self, handle: u32, data: &[u8], width: u32, height: u32) -> Result<(), JsValue> {
+        SecondaryImage::new(data, width, height, 0, 0)
+            .ok_or_else(|| JsValue::from_str("Secondary image data does not match its dimensions"))?;
+        if self.images.replace(handle, data.to_vec(), width, height) {
+            Ok(())
+        } else {
+            Err(JsValue::from_str("Unknown secondary image handle"))
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn release_image(&mut self, handle: u32) -> bool {
+        self.images.remove(handle)
+    }
+
+    // Same as `apply_effects`, but processes an `ImageBuffer` in place without any copies
+    #[wasm_bindgen]
+    pub fn apply_effects_to_buffer(&mut self, buffer: &mut ImageBuffer, options_js: JsValue) -> Result<(), JsValue> {
+        let options: GlitchOptions = serde_wasm_bindgen::from_value(options_js)?;
+        let width = buffer.width();
+        self.apply_options_internal(buffer.pixels_mut(), width, options);
+        Ok(())
+    }
+
+    // Same pipeline as `apply_effects`, but as a job that `run_job` advances a bit at a time.
+    // Seed, modulation and audio are resolved here; the effect's random stream is then used
+    // by the job's steps, so avoid other renders on this instance until it finishes.
+    #[wasm_bindgen]
+    pub fn start_job(&mut self, image_data: ImageData, options_js: JsValue) -> Result<RenderJob, JsValue> {
+        let mut options: GlitchOptions = serde_wasm_bindgen::from_value(options_js)?;
+        self.prepare_options_internal(&mut options);
+        Ok(RenderJob::new(image_data.data().to_vec(), image_data.width(), image_data.height(), options))
+    }
+
+    // Run steps for up to `budget_ms` milliseconds (at least one step), then report progress.
+    // Returns true once there is nothing left to do, because the job finished or was cancelled.
+    #[wasm_bindgen]
+    pub fn run_job(&mut self, job: &mut RenderJob, budget_ms: f64) -> Result<bool, JsValue> {
+        let start = js_sys::Date::now();
+        loop {
+            job.step(self);
+            if job.is_finished() || js_sys::Date::now() - start >= budget_ms {
+                break;
+            }
+        }
+        job.report()?;
+        Ok(job.is_finished())
+    }
+
+    // Live preview: shrink the image so its longer side is at most `max_size` and render the
+    // same options on it. With a fixed seed, `apply_effects` on the full image gives the
+    // full-resolution version of this preview.
+    #[wasm_bindgen]
+    pub fn render_proxy(&mut self, image_data: ImageData, options_js: JsValue, max_size: u32) -> Result<ImageData, JsValue> {
+        let options: GlitchOptions = serde_wasm_bindgen::from_value(options_js)?;
+        self.render_proxy_internal(&image_data, options, max_size)
+    }
+
+    // Thumbnail of one individual of a breeding population, rendered like `render_proxy`
+    #[wasm_bindgen]
+    pub fn render_thumbnail(&mut self, population: &Population, id: usize, image_data: ImageData, max_size: u32) -> Result<ImageData, JsValue> {
+        let options = population.recipe_internal(id)?.clone().into_options(None);
+        self.render_proxy_internal(&image_data, options, max_size)
+    }
+
+    // Render options on a history's original image, reusing the cached results of the steps
+    // before the first one that changed. With `options_js` undefined the history's current
+    // entry is rendered, so undo/redo can show a cached result straight away.
+    #[wasm_bindgen]
+    pub fn render_history(&mut self, history: &mut EffectHistory, options_js: JsValue) -> Result<ImageData, JsValue> {
+        let options = if options_js.is_undefined() || options_js.is_null() {
+            history.current_options()
+        } else {
+            serde_wasm_bindgen::from_value(options_js)?
         };
-        self.image_blend_internal(data, width, &options);
+        let data = history.render(self, options);
+        ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&data), history.width(), history.height())
     }
 
     #[wasm_bindgen]
@@ -775,60 +1383,55 @@ impl GlitchEffect {
         // Create a copy of the data for processing
         let original_data = image_data.data();
         let mut data = original_data.to_vec();
-        
-        // Apply pixel sort effect if requested
-        if let Some(pixel_sort_options) = &options.pixel_sort {
-            self.pixel_sort_internal(&mut data, width, &pixel_sort_options);
-        }
-        
-        // Apply data bend effect if requested
-        if let Some(data_bend_options) = &options.data_bend {
-            self.data_bend_internal(&mut data, &data_bend_options);
-        }
-        
-        // Apply channel shift effect if requested
-        if let Some(channel_shift_options) = &options.channel_shift {
-            self.channel_shift_internal(&mut data, &channel_shift_options);
-        }
-        
-        // Apply noise if requested
-        if let Some(amount) = options.noise {
-            self.add_noise_internal(&mut data, amount);
-        }
-        
-        // Apply invert if requested
-        if let Some(channels) = &options.invert {
-            self.invert_channels_internal(&mut data, channels);
-        }
-        
-        // Apply quantize if requested
-        if let Some(levels) = options.quantize {
-            self.quantize_internal(&mut data, levels);
-        }
-        
-        // Apply byte corruption if requested
-        if let Some(byte_corrupt_options) = &options.byte_corrupt {
-            self.byte_corrupt_internal(&mut data, &byte_corrupt_options);
-        }
-        
-        // Apply chunk swap if requested
-        if let Some(chunk_swap_options) = &options.chunk_swap {
-            self.chunk_swap_internal(&mut data, width, &chunk_swap_options);
-        }
-        
-        // Apply binary XOR if requested
-        if let Some(binary_xor_options) = &options.binary_xor {
-            self.binary_xor_internal(&mut data, width, &binary_xor_options);
-        }
-        
-        // Apply image blend if requested
-        if let Some(image_blend_options) = &options.image_blend {
-            self.image_blend_internal(&mut data, width, &image_blend_options);
-        }
-        
+
+        self.apply_options_internal(&mut data, width, options);
+
         // Create new ImageData
         // Convert Vec<u8> to slice &[u8] to match expected type
         let data_slice = data.as_slice();
         ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(data_slice), width, height)
     }
+
+    // Render a recipe (any version, see recipe.rs) with its own seed. `time` picks the frame
+    // of an animated recipe. Assets must be registered with `register_image` beforehand.
+    #[wasm_bindgen]
+    pub fn apply_recipe(&mut self, image_data: ImageData, recipe_js: JsValue, time: Option<f64>) -> Result<ImageData, JsValue> {
+        let options = recipe::from_js(recipe_js)?.into_options(time);
+        let (width, height) = (image_data.width(), image_data.height());
+        let mut data = image_data.data().to_vec();
+        self.apply_options_internal(&mut data, width, options);
+        ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&data), width, height)
+    }
+}
+
+impl GlitchEffect {
+    fn render_proxy_internal(&mut self, image_data: &ImageData, options: GlitchOptions, max_size: u32) -> Result<ImageData, JsValue> {
+        let (width, height) = (image_data.width(), image_data.height());
+        let (proxy_width, proxy_height) = proxy::proxy_size(width, height, max_size);
+
+        // Bilinear widens into an area filter when shrinking, so the proxy averages what it covers
+        let mut data = transform::resize(&image_data.data(), width as usize, height as usize,
+                                         proxy_width as usize, proxy_height as usize, 1);
+        self.apply_options_internal(&mut data, proxy_width, options);
+        ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(&data), proxy_width, proxy_height)
+    }
+
+    // Seed, modulation and audio mapping, then the full effect pipeline
+    fn apply_options_internal(&mut self, data: &mut [u8], width: u32, mut options: GlitchOptions) {
+        self.prepare_options_internal(&mut options);
+        self.process_internal(data, width, &options);
+    }
+
+    // Per-frame setup shared by direct renders and jobs
+    fn prepare_options_internal(&mut self, options: &mut GlitchOptions) {
+        if let Some(seed) = options.seed {
+            self.rng = SmallRng::seed_from_u64(seed);
+        }
+
+        // Resolve modulated parameter values for this frame
+        if let Some(time) = options.time {
+            modulation::apply_modulation(options, time);
+        }
+        audio::apply_audio(options);
+    }
 } 
\ No newline at end of file
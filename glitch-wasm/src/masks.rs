@@ -0,0 +1,327 @@
+// Content-aware masks: maps computed from the image (edges, local contrast, saliency, skin
+// tone) that confine any effect of the pipeline to parts of the picture or keep it away from
+// them, e.g. so faces stay recognizable while the background melts.
+//
+// Maps come from the image as it enters the pipeline, so earlier effects don't move the
+// protected regions. A masked effect runs on the whole image as usual and its result is then
+// mixed with the pixels from before it, pixel by pixel, using the mask as the weight.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::job::Stage;
+use crate::yuv::rgb_to_ycbcr;
+use crate::{spatial_scale, GlitchOptions};
+
+// Number of map sources (see `StageMask::source`)
+const SOURCES: usize = 5;
+
+// Canny hysteresis thresholds on the normalized gradient magnitude (a full black to white
+// step is 1.0)
+const CANNY_HIGH: f32 = 0.2;
+const CANNY_LOW: f32 = 0.08;
+
+// Window radius in pixels (scaled with the image) for local contrast, and the blur radius
+// applied before saliency and Canny edges
+const CONTRAST_RADIUS: f64 = 8.0;
+const SALIENCY_BLUR: f64 = 3.0;
+const CANNY_BLUR: f64 = 1.5;
+
+// Center and radii of the skin tone ellipse in the CbCr plane
+const SKIN_CB: f32 = 102.0;
+const SKIN_CR: f32 = 153.0;
+const SKIN_CB_RADIUS: f32 = 25.0;
+const SKIN_CR_RADIUS: f32 = 20.0;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StageMask {
+    pub(crate) effect: String,         // Stage name as in `order`, e.g. "pixel_sort" or "data_bend"
+    pub(crate) source: usize,          // 0=Sobel edges, 1=Canny edges, 2=local contrast, 3=saliency, 4=skin tone
+    pub(crate) mode: Option<usize>,    // 0=mask (effect only where the map is above `threshold`), 1=intensity (effect strength follows the map) (default: 0)
+    pub(crate) invert: Option<bool>,   // Keep the effect away from the mapped regions instead
+    pub(crate) threshold: Option<f64>, // Cutoff for mask mode (0.0-1.0, default: 0.5)
+    pub(crate) softness: Option<f64>,  // Feather radius in pixels, scaled with the image (default: 0)
+    pub(crate) amount: Option<f64>,    // Overall strength of the masked effect (0.0-1.0, default: 1)
+}
+
+// Map sources computed once per render for the masks in the options
+pub(crate) struct Maps {
+    width: usize,
+    height: usize,
+    scale: f64,
+    sources: [Option<Vec<f32>>; SOURCES],
+}
+
+// How a map becomes weights: thresholded (mode 0) or used as-is (mode 1), optionally
+// inverted, then feathered by `radius` pixels
+pub(crate) struct Shape {
+    pub(crate) mode: usize,
+    pub(crate) invert: bool,
+    pub(crate) threshold: f32,
+    pub(crate) radius: usize,
+}
+
+fn shape_map(map: &[f32], width: usize, height: usize, shape: &Shape) -> Vec<f32> {
+    let mut weights: Vec<f32> = map.iter()
+        .map(|&value| if shape.invert { 1.0 - value } else { value })
+        .map(|value| match shape.mode {
+            1 => value,
+            _ => if value >= shape.threshold { 1.0 } else { 0.0 },
+        })
+        .collect();
+    if shape.radius > 0 {
+        feather(&mut weights, width, height, shape.radius);
+    }
+    weights
+}
+
+impl Maps {
+    pub(crate) fn new(data: &[u8], width: u32, options: &GlitchOptions) -> Self {
+        let scale = spatial_scale(data.len(), options.absolute_units.unwrap_or(false));
+        Self::with_sources(data, width, scale, options.masks.iter().flatten().map(|mask| mask.source))
+    }
+
+    pub(crate) fn with_sources(data: &[u8], width: u32, scale: f64, wanted: impl Iterator<Item = usize>) -> Self {
+        let width = width as usize;
+        let height = (data.len() / 4).checked_div(width).unwrap_or(0);
+        let mut sources: [Option<Vec<f32>>; SOURCES] = Default::default();
+        for source in wanted {
+            if source < SOURCES && sources[source].is_none() {
+                sources[source] = Some(content_map_internal(data, width, height, source, scale));
+            }
+        }
+        Self { width, height, scale, sources }
+    }
+
+    pub(crate) fn map(&self, source: usize) -> Option<&[f32]> {
+        self.sources.get(source)?.as_deref()
+    }
+
+    // Feather radius in pixels for a softness option
+    pub(crate) fn radius(&self, softness: Option<f64>) -> usize {
+        (softness.unwrap_or(0.0).max(0.0) * self.scale).round() as usize
+    }
+
+    pub(crate) fn shape(&self, map: &[f32], shape: &Shape) -> Vec<f32> {
+        shape_map(map, self.width, self.height, shape)
+    }
+
+    // Per-pixel weights (0.0-1.0) for a stage, None when no mask applies to it. Several
+    // masks for the same effect multiply, so the effect only shows where all of them allow it.
+    pub(crate) fn weights(&self, options: &GlitchOptions, stage: Stage) -> Option<Vec<f32>> {
+        let mut weights: Option<Vec<f32>> = None;
+        for mask in options.masks.iter().flatten().filter(|mask| mask.effect == stage.name()) {
+            let Some(map) = self.map(mask.source) else { continue; };
+            let layer = self.shape(map, &Shape {
+                mode: mask.mode.unwrap_or(0),
+                invert: mask.invert.unwrap_or(false),
+                threshold: mask.threshold.unwrap_or(0.5) as f32,
+                radius: self.radius(mask.softness),
+            });
+            let amount = mask.amount.unwrap_or(1.0).clamp(0.0, 1.0) as f32;
+            match &mut weights {
+                Some(weights) => weights.iter_mut().zip(&layer).for_each(|(w, l)| *w *= l * amount),
+                None => weights = Some(layer.into_iter().map(|l| l * amount).collect()),
+            }
+        }
+        weights
+    }
+}
+
+// Mix a stage's result with the pixels from before it: weight 1 keeps the effect, 0 undoes it
+pub(crate) fn blend(data: &mut [u8], before: &[u8], weights: &[f32]) {
+    for ((pixel, original), &weight) in data.chunks_exact_mut(4).zip(before.chunks_exact(4)).zip(weights) {
+        for (value, &from) in pixel.iter_mut().zip(original) {
+            *value = (from as f32 + (*value as f32 - from as f32) * weight).round() as u8;
+        }
+    }
+}
+
+fn luma(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(4).map(|pixel| rgb_to_ycbcr(pixel[0], pixel[1], pixel[2])[0] as f32 / 255.0).collect()
+}
+
+// Map for one source with values in 0.0-1.0, one per pixel
+fn content_map_internal(data: &[u8], width: usize, height: usize, source: usize, scale: f64) -> Vec<f32> {
+    if width == 0 || height == 0 { return Vec::new(); }
+    let radius = |pixels: f64| ((pixels * scale).round() as usize).max(1);
+    match source {
+        0 => {
+            let (gx, gy) = gradients(&luma(data), width, height);
+            gx.iter().zip(&gy).map(|(x, y)| x.hypot(*y).min(1.0)).collect()
+        }
+        1 => {
+            let mut luma = luma(data);
+            feather(&mut luma, width, height, radius(CANNY_BLUR));
+            canny(&luma, width, height)
+        }
+        2 => local_contrast(&luma(data), width, height, radius(CONTRAST_RADIUS)),
+        3 => saliency(data, width, height, radius(SALIENCY_BLUR)),
+        4 => data.chunks_exact(4).map(|pixel| skin_tone(pixel[0], pixel[1], pixel[2])).collect(),
+        _ => vec![0.0; width * height],
+    }
+}
+
+// Sobel gradients, normalized so a full black to white step gives 1.0. Edges are clamped.
+pub(crate) fn gradients(luma: &[f32], width: usize, height: usize) -> (Vec<f32>, Vec<f32>) {
+    let at = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        luma[y * width + x]
+    };
+    let mut gx = vec![0.0; luma.len()];
+    let mut gy = vec![0.0; luma.len()];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let i = y as usize * width + x as usize;
+            gx[i] = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1) - 2.0 * at(x - 1, y) - at(x - 1, y + 1)) / 4.0;
+            gy[i] = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1) - 2.0 * at(x, y - 1) - at(x + 1, y - 1)) / 4.0;
+        }
+    }
+    (gx, gy)
+}
+
+// Canny edges of an already smoothed luminance: thin the gradient to its ridges, then keep
+// strong edges and the weak edges connected to them. 1.0 on an edge, 0.0 elsewhere.
+fn canny(luma: &[f32], width: usize, height: usize) -> Vec<f32> {
+    let (gx, gy) = gradients(luma, width, height);
+    let magnitude: Vec<f32> = gx.iter().zip(&gy).map(|(x, y)| x.hypot(*y)).collect();
+    let at = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize { 0.0 } else { magnitude[y as usize * width + x as usize] }
+    };
+
+    // Non-maximum suppression along the gradient direction, rounded to 45 degrees
+    let mut thin = vec![0.0f32; magnitude.len()];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let i = y as usize * width + x as usize;
+            let angle = gy[i].atan2(gx[i]).to_degrees().rem_euclid(180.0);
+            let (dx, dy) = match angle {
+                a if !(22.5..157.5).contains(&a) => (1, 0),
+                a if a < 67.5 => (1, 1),
+                a if a < 112.5 => (0, 1),
+                _ => (-1, 1),
+            };
+            let m = magnitude[i];
+            if m >= at(x + dx, y + dy) && m >= at(x - dx, y - dy) {
+                thin[i] = m;
+            }
+        }
+    }
+
+    // Hysteresis: grow from the strong edges through the weak ones
+    let mut edges = vec![0.0f32; thin.len()];
+    let mut stack: Vec<usize> = (0..thin.len()).filter(|&i| thin[i] >= CANNY_HIGH).collect();
+    for &i in &stack {
+        edges[i] = 1.0;
+    }
+    while let Some(i) = stack.pop() {
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize { continue; }
+                let n = ny as usize * width + nx as usize;
+                if edges[n] == 0.0 && thin[n] >= CANNY_LOW {
+                    edges[n] = 1.0;
+                    stack.push(n);
+                }
+            }
+        }
+    }
+    edges
+}
+
+// Standard deviation of the luminance around each pixel. A deviation of 0.25 (half the
+// largest possible) or more counts as full contrast.
+fn local_contrast(luma: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let mut mean = luma.to_vec();
+    let mut mean_squares: Vec<f32> = luma.iter().map(|v| v * v).collect();
+    box_blur(&mut mean, width, height, radius);
+    box_blur(&mut mean_squares, width, height, radius);
+    mean.iter().zip(&mean_squares)
+        .map(|(m, s)| ((s - m * m).max(0.0).sqrt() * 4.0).min(1.0))
+        .collect()
+}
+
+// Frequency-tuned saliency: how far each (slightly blurred) pixel's color is from the
+// image's average color in YCbCr, relative to the most distinct pixel
+fn saliency(data: &[u8], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let mut planes = [Vec::new(), Vec::new(), Vec::new()];
+    for pixel in data.chunks_exact(4) {
+        let ycc = rgb_to_ycbcr(pixel[0], pixel[1], pixel[2]);
+        for (plane, value) in planes.iter_mut().zip(ycc) {
+            plane.push(value as f32);
+        }
+    }
+    let means = planes.each_ref().map(|plane| plane.iter().sum::<f32>() / plane.len().max(1) as f32);
+    for plane in &mut planes {
+        box_blur(plane, width, height, radius);
+    }
+    let distances: Vec<f32> = (0..width * height)
+        .map(|i| (0..3).map(|c| (planes[c][i] - means[c]).powi(2)).sum::<f32>().sqrt())
+        .collect();
+    let max = distances.iter().copied().fold(0.0, f32::max);
+    if max == 0.0 {
+        return vec![0.0; distances.len()];
+    }
+    distances.into_iter().map(|d| d / max).collect()
+}
+
+// Soft skin tone key: 1.0 at the center of the usual CbCr range, falling to 0.0 at its
+// edge. Very dark pixels are faded out since their chroma is unreliable.
+fn skin_tone(r: u8, g: u8, b: u8) -> f32 {
+    let [y, cb, cr] = rgb_to_ycbcr(r, g, b).map(|v| v as f32);
+    let distance = (((cb - SKIN_CB) / SKIN_CB_RADIUS).powi(2) + ((cr - SKIN_CR) / SKIN_CR_RADIUS).powi(2)).sqrt();
+    let chroma = ((1.0 - distance) * 2.0).clamp(0.0, 1.0);
+    let brightness = ((y - 40.0) / 40.0).clamp(0.0, 1.0);
+    chroma * brightness
+}
+
+// Two box blur passes, close to a Gaussian with about this radius
+fn feather(map: &mut [f32], width: usize, height: usize, radius: usize) {
+    let pass = radius.div_ceil(2);
+    box_blur(map, width, height, pass);
+    box_blur(map, width, height, pass);
+}
+
+// Separable box blur with clamped edges
+fn box_blur(map: &mut [f32], width: usize, height: usize, radius: usize) {
+    if radius == 0 || width == 0 || height == 0 { return; }
+    let mut line = Vec::with_capacity(width.max(height));
+    for y in 0..height {
+        blur_line(map, y * width, 1, width, radius, &mut line);
+    }
+    for x in 0..width {
+        blur_line(map, x, width, height, radius, &mut line);
+    }
+}
+
+// Running-sum box blur of `length` values starting at `start`, `stride` apart
+fn blur_line(map: &mut [f32], start: usize, stride: usize, length: usize, radius: usize, line: &mut Vec<f32>) {
+    line.clear();
+    line.extend((0..length).map(|i| map[start + i * stride]));
+    let at = |i: isize| line[i.clamp(0, length as isize - 1) as usize];
+    let window = (2 * radius + 1) as f32;
+    let radius = radius as isize;
+    let mut sum: f32 = (-radius..=radius).map(at).sum();
+    for i in 0..length {
+        map[start + i * stride] = sum / window;
+        sum += at(i as isize + radius + 1) - at(i as isize - radius);
+    }
+}
+
+// Preview of a content map for the UI: one byte (0-255) per pixel, sized like the image.
+// `source` is as in the `masks` option: 0=Sobel edges, 1=Canny edges, 2=local contrast,
+// 3=saliency, 4=skin tone.
+#[wasm_bindgen]
+pub fn content_map(data: &[u8], width: u32, source: usize) -> Vec<u8> {
+    let height = (data.len() / 4).checked_div(width as usize).unwrap_or(0);
+    let scale = spatial_scale(data.len(), false);
+    content_map_internal(data, width as usize, height, source, scale)
+        .into_iter()
+        .map(|value| (value * 255.0).round() as u8)
+        .collect()
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+
+use crate::channels::ChannelEffects;
+use crate::GlitchOptions;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModulatorOptions {
+    pub(crate) shape: usize,          // 0=sine, 1=triangle, 2=square, 3=saw, 4=sample & hold, 5=Perlin noise, 6=envelope, 7=simplex noise
+    pub(crate) rate: f64,             // Cycles per second (LFOs, sample & hold, noise)
+    pub(crate) phase: Option<f64>,    // Phase offset in cycles (0.0-1.0)
+    pub(crate) seed: Option<u64>,     // Seed for random shapes (defaults to the options seed)
+    pub(crate) attack: Option<f64>,   // Envelope attack time in seconds
+    pub(crate) decay: Option<f64>,    // Envelope decay time in seconds
+    pub(crate) sustain: Option<f64>,  // Envelope sustain level (0.0-1.0)
+    pub(crate) release: Option<f64>,  // Envelope release time in seconds
+    pub(crate) trigger: Option<f64>,  // Envelope trigger time in seconds (default: 0)
+    pub(crate) gate: Option<f64>,     // How long the envelope is held before releasing (default: attack + decay)
+}
+
+// Parameters that modulation routes and audio mappings can drive. Unknown paths are
+// rejected when options are parsed; layers, YUV planes, channel chains and the
+// integer/boolean options can't be modulated yet.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ModulationTarget {
+    #[serde(rename = "pixel_sort.intensity")] PixelSortIntensity,
+    #[serde(rename = "pixel_sort.threshold")] PixelSortThreshold,
+    #[serde(rename = "pixel_sort.auto_target")] PixelSortAutoTarget,
+    #[serde(rename = "data_bend.amount")] DataBendAmount,
+    #[serde(rename = "data_bend.chunk_size")] DataBendChunkSize,
+    #[serde(rename = "channel_shift.amount")] ChannelShiftAmount,
+    #[serde(rename = "noise")] Noise,
+    #[serde(rename = "byte_corrupt.amount")] ByteCorruptAmount,
+    #[serde(rename = "chunk_swap.amount")] ChunkSwapAmount,
+    #[serde(rename = "chunk_swap.chunk_size")] ChunkSwapChunkSize,
+    #[serde(rename = "binary_xor.strength")] BinaryXorStrength,
+    #[serde(rename = "image_blend.amount")] ImageBlendAmount,
+    #[serde(rename = "sonify.amount")] SonifyAmount,
+    #[serde(rename = "fourier.amount")] FourierAmount,
+    #[serde(rename = "transform.rotation")] TransformRotation,
+    #[serde(rename = "transform.zoom")] TransformZoom,
+    #[serde(rename = "red_channel.pixel_sort.intensity")] RedPixelSortIntensity,
+    #[serde(rename = "red_channel.data_bend.amount")] RedDataBendAmount,
+    #[serde(rename = "red_channel.noise")] RedNoise,
+    #[serde(rename = "green_channel.pixel_sort.intensity")] GreenPixelSortIntensity,
+    #[serde(rename = "green_channel.data_bend.amount")] GreenDataBendAmount,
+    #[serde(rename = "green_channel.noise")] GreenNoise,
+    #[serde(rename = "blue_channel.pixel_sort.intensity")] BluePixelSortIntensity,
+    #[serde(rename = "blue_channel.data_bend.amount")] BlueDataBendAmount,
+    #[serde(rename = "blue_channel.noise")] BlueNoise,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModulationRoute {
+    pub(crate) target: ModulationTarget,          // Parameter path, e.g. "data_bend.amount" or "pixel_sort.threshold"
+    pub(crate) modulator: ModulatorOptions,
+    pub(crate) depth: f64,              // How far the modulator moves the parameter
+    pub(crate) offset: Option<f64>,     // Constant added to the base value
+    pub(crate) min: Option<f64>,        // Lower clamp for the result (default: the target's range, usually 0)
+    pub(crate) max: Option<f64>,        // Upper clamp for the result (default: the target's range, usually 1)
+}
+
+// Stateless 64-bit mixer (SplitMix64 finalizer), so modulators are a pure function of seed and time
+pub(crate) fn hash64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+// Random value in -1.0..1.0 for an integer lattice point
+fn lattice_value(seed: u64, index: i64) -> f64 {
+    let h = hash64(seed ^ hash64(index as u64));
+    (h >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+}
+
+// 1D Perlin gradient noise, roughly in -1.0..1.0
+fn perlin_1d(seed: u64, x: f64) -> f64 {
+    let x0 = x.floor();
+    let t = x - x0;
+    let i = x0 as i64;
+    let g0 = lattice_value(seed, i);
+    let g1 = lattice_value(seed, i + 1);
+    let d0 = g0 * t;
+    let d1 = g1 * (t - 1.0);
+    let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    // The 1D gradient noise peaks at +-0.5, so scale to the full range
+    (d0 + (d1 - d0) * fade) * 2.0
+}
+
+// 1D simplex noise in -1.0..1.0: each lattice point's gradient fades out with (1 - d^2)^4
+fn simplex_1d(seed: u64, x: f64) -> f64 {
+    let x0 = x.floor();
+    let i = x0 as i64;
+    let d0 = x - x0;
+    let d1 = d0 - 1.0;
+    let falloff = |d: f64| (1.0 - d * d).powi(4) * d;
+    let n = falloff(d0) * lattice_value(seed, i) + falloff(d1) * lattice_value(seed, i + 1);
+    // The sum peaks at 81/256 halfway between lattice points with opposite gradients
+    n * 256.0 / 81.0
+}
+
+fn envelope(options: &ModulatorOptions, time: f64) -> f64 {
+    let attack = options.attack.unwrap_or(0.1).max(0.0);
+    let decay = options.decay.unwrap_or(0.2).max(0.0);
+    let sustain = options.sustain.unwrap_or(0.5).clamp(0.0, 1.0);
+    let release = options.release.unwrap_or(0.5).max(0.0);
+    let gate = options.gate.unwrap_or(attack + decay).max(0.0);
+    let t = time - options.trigger.unwrap_or(0.0);
+
+    // Level of the attack/decay/sustain stage at a given time since the trigger
+    let held_level = |t: f64| -> f64 {
+        if t < attack {
+            t / attack
+        } else if t < attack + decay {
+            1.0 - (1.0 - sustain) * (t - attack) / decay
+        } else {
+            sustain
+        }
+    };
+
+    if t < 0.0 {
+        0.0
+    } else if t < gate {
+        held_level(t)
+    } else if release > 0.0 && t < gate + release {
+        held_level(gate) * (1.0 - (t - gate) / release)
+    } else {
+        0.0
+    }
+}
+
+// Evaluate a modulator at a time in seconds. LFOs and noise are bipolar (-1.0..1.0),
+// the envelope is unipolar (0.0..1.0).
+pub fn evaluate(options: &ModulatorOptions, time: f64, default_seed: u64) -> f64 {
+    let seed = options.seed.unwrap_or(default_seed);
+    let position = time * options.rate + options.phase.unwrap_or(0.0);
+    let cycle = position.rem_euclid(1.0);
+
+    match options.shape {
+        0 => (cycle * std::f64::consts::TAU).sin(),
+        1 => 1.0 - 4.0 * (cycle - 0.5).abs(),
+        2 => if cycle < 0.5 { 1.0 } else { -1.0 },
+        3 => cycle * 2.0 - 1.0,
+        4 => lattice_value(seed, position.floor() as i64),
+        5 => perlin_1d(seed, position).clamp(-1.0, 1.0),
+        6 => envelope(options, time),
+        7 => simplex_1d(seed, position).clamp(-1.0, 1.0),
+        _ => 0.0,
+    }
+}
+
+// Resolve a target to the value it controls, or None when its effect is off. Optional
+// parameters that are unset get their default so they can still be modulated.
+pub(crate) fn target_param(options: &mut GlitchOptions, target: ModulationTarget) -> Option<&mut f64> {
+    match target {
+        ModulationTarget::PixelSortIntensity => options.pixel_sort.as_mut().map(|o| &mut o.intensity),
+        ModulationTarget::PixelSortThreshold => options.pixel_sort.as_mut().map(|o| &mut o.threshold),
+        ModulationTarget::PixelSortAutoTarget => options.pixel_sort.as_mut().map(|o| o.auto_target.get_or_insert(0.5)),
+        ModulationTarget::DataBendAmount => options.data_bend.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::DataBendChunkSize => options.data_bend.as_mut().map(|o| o.chunk_size.get_or_insert(0.5)),
+        ModulationTarget::ChannelShiftAmount => options.channel_shift.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::Noise => options.noise.as_mut(),
+        ModulationTarget::ByteCorruptAmount => options.byte_corrupt.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::ChunkSwapAmount => options.chunk_swap.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::ChunkSwapChunkSize => options.chunk_swap.as_mut().map(|o| o.chunk_size.get_or_insert(0.5)),
+        ModulationTarget::BinaryXorStrength => options.binary_xor.as_mut().map(|o| &mut o.strength),
+        ModulationTarget::ImageBlendAmount => options.image_blend.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::SonifyAmount => options.sonify.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::FourierAmount => options.fourier.as_mut().map(|o| &mut o.amount),
+        ModulationTarget::TransformRotation => options.transform.as_mut().map(|o| o.rotation.get_or_insert(0.0)),
+        ModulationTarget::TransformZoom => options.transform.as_mut().map(|o| o.zoom.get_or_insert(1.0)),
+        ModulationTarget::RedPixelSortIntensity => channel_param(&mut options.red_channel, ChannelParam::PixelSortIntensity),
+        ModulationTarget::RedDataBendAmount => channel_param(&mut options.red_channel, ChannelParam::DataBendAmount),
+        ModulationTarget::RedNoise => channel_param(&mut options.red_channel, ChannelParam::Noise),
+        ModulationTarget::GreenPixelSortIntensity => channel_param(&mut options.green_channel, ChannelParam::PixelSortIntensity),
+        ModulationTarget::GreenDataBendAmount => channel_param(&mut options.green_channel, ChannelParam::DataBendAmount),
+        ModulationTarget::GreenNoise => channel_param(&mut options.green_channel, ChannelParam::Noise),
+        ModulationTarget::BluePixelSortIntensity => channel_param(&mut options.blue_channel, ChannelParam::PixelSortIntensity),
+        ModulationTarget::BlueDataBendAmount => channel_param(&mut options.blue_channel, ChannelParam::DataBendAmount),
+        ModulationTarget::BlueNoise => channel_param(&mut options.blue_channel, ChannelParam::Noise),
+    }
+}
+
+// Parameters of a single channel's effects that can be modulated
+enum ChannelParam {
+    PixelSortIntensity,
+    DataBendAmount,
+    Noise,
+}
+
+fn channel_param(effects: &mut Option<ChannelEffects>, param: ChannelParam) -> Option<&mut f64> {
+    let effects = effects.as_mut()?;
+    match param {
+        ChannelParam::PixelSortIntensity => effects.pixel_sort.as_mut().map(|o| &mut o.intensity),
+        ChannelParam::DataBendAmount => effects.data_bend.as_mut().map(|o| &mut o.amount),
+        ChannelParam::Noise => effects.noise.as_mut(),
+    }
+}
+
+// Range a target's value is clamped to unless a route sets min/max
+fn default_range(target: ModulationTarget) -> (f64, f64) {
+    match target {
+        ModulationTarget::TransformRotation => (-180.0, 180.0), // Degrees
+        ModulationTarget::TransformZoom => (0.1, 8.0),          // Factor
+        _ => (0.0, 1.0),
+    }
+}
+
+// Move a target parameter by `depth * value` plus an offset, clamped to min/max (default:
+// the target's range)
+pub(crate) fn modulate_target(options: &mut GlitchOptions, target: ModulationTarget, value: f64, depth: f64,
+                              offset: Option<f64>, min: Option<f64>, max: Option<f64>) {
+    let (low, high) = default_range(target);
+    if let Some(param) = target_param(options, target) {
+        let modulated = *param + offset.unwrap_or(0.0) + depth * value;
+        *param = modulated.max(min.unwrap_or(low)).min(max.unwrap_or(high));
+    }
+}
+
+// Apply every route in the options to their target parameters at the given time
+pub(crate) fn apply_modulation(options: &mut GlitchOptions, time: f64) {
+    let routes = match options.modulation.take() {
+        Some(routes) => routes,
+        None => return,
+    };
+    let seed = options.seed.unwrap_or(0);
+
+    for route in &routes {
+        let value = evaluate(&route.modulator, time, seed);
+        modulate_target(options, route.target, value, route.depth, route.offset, route.min, route.max);
+    }
+
+    options.modulation = Some(routes);
+}
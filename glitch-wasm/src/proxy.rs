@@ -0,0 +1,17 @@
+// Downscaled copies of an image for live preview. Effects use resolution-independent
+// parameters and normalized random positions, so rendering a proxy with the same options
+// and seed gives a scaled-down version of the full-resolution export. The downscale itself
+// is `transform::resize`.
+
+// Largest size with the same aspect ratio whose longer side is at most `max_size`
+pub fn proxy_size(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if max_size == 0 || longest <= max_size {
+        return (width, height);
+    }
+    let ratio = max_size as f64 / longest as f64;
+    (
+        ((width as f64 * ratio).round() as u32).max(1),
+        ((height as f64 * ratio).round() as u32).max(1),
+    )
+}
@@ -0,0 +1,664 @@
+// Random recipes ("surprise me") and variations of an existing one ("more like this").
+// Everything is drawn from a seeded generator, so the same seed always gives the same
+// result, and every value stays within the range its option accepts.
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use wasm_bindgen::prelude::*;
+
+use crate::bitplane::{BitPlaneOptions, BitSwap};
+use crate::blend;
+use crate::channels::{ChannelChain, ChannelEffects};
+use crate::fourier::FourierOptions;
+use crate::job::{self, Stage};
+use crate::layers::Layer;
+use crate::recipe::{self, Recipe};
+use crate::reinterpret::ReinterpretOptions;
+use crate::sonify::SonifyOptions;
+use crate::yuv::YuvOptions;
+use crate::{
+    BinaryXorOptions, ByteCorruptOptions, ChannelShiftOptions, ChunkSwapOptions, DataBendOptions,
+    GlitchOptions, ImageBlendOptions, PixelSortOptions,
+};
+
+// Limits for integer options that are only bounded by their type, like offsets. Mutation
+// steps them by their usual span (see `Dice::step_span`) without clipping larger values.
+const INT_MIN: i64 = i32::MIN as i64;
+const INT_MAX: i64 = i32::MAX as i64;
+
+// Stages the randomizer can turn on, weighted by how often they should appear. Image blend
+// needs a secondary image, so it is only ever mutated.
+const RANDOM_STAGES: [(Stage, f64); 15] = [
+    (Stage::PixelSort, 1.0),
+    (Stage::DataBend, 1.0),
+    (Stage::ChannelShift, 1.0),
+    (Stage::Noise, 0.6),
+    (Stage::Invert, 0.3),
+    (Stage::Quantize, 0.6),
+    (Stage::ByteCorrupt, 0.8),
+    (Stage::ChunkSwap, 0.8),
+    (Stage::BinaryXor, 0.6),
+    (Stage::Sonify, 0.6),
+    (Stage::Fourier, 0.5),
+    (Stage::BitPlane, 0.5),
+    (Stage::Reinterpret, 0.3),
+    (Stage::Yuv, 0.6),
+    (Stage::ChannelChains, 0.5),
+];
+
+// New seeds stay within the integers a JS number holds exactly
+pub(crate) const MAX_SEED: u64 = (1 << 53) - 1;
+
+// Seeded generator plus how strong its changes are: the chaos level when generating,
+// the mutation amount when mutating (both 0.0-1.0)
+struct Dice {
+    rng: SmallRng,
+    amount: f64,
+}
+
+impl Dice {
+    fn new(seed: u64, amount: f64) -> Self {
+        Self { rng: SmallRng::seed_from_u64(seed), amount: amount.clamp(0.0, 1.0) }
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.rng.gen_bool(probability.clamp(0.0, 1.0))
+    }
+
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        self.rng.gen_range(min..=max)
+    }
+
+    fn index(&mut self, n: usize) -> usize {
+        self.rng.gen_range(0..n)
+    }
+
+    fn offset(&mut self, max: i32) -> i32 {
+        self.rng.gen_range(-max..=max)
+    }
+
+    // Strength of a new effect: gentle at low chaos, anything up to full at high chaos
+    fn strength(&mut self) -> f64 {
+        self.range(0.05, 0.2 + 0.8 * self.amount)
+    }
+
+    // Non-empty random subset of 0..n
+    fn subset(&mut self, n: usize) -> Vec<usize> {
+        let mut members: Vec<usize> = (0..n).filter(|_| self.rng.gen_bool(0.5)).collect();
+        if members.is_empty() {
+            members.push(self.index(n));
+        }
+        members
+    }
+
+    // Move a value by up to `amount` of half its range, staying inside the range
+    fn nudge(&mut self, value: &mut f64, min: f64, max: f64) {
+        let step = (max - min) * 0.5 * self.amount;
+        *value = (*value + self.range(-step, step)).clamp(min, max);
+    }
+
+    fn nudge_opt(&mut self, value: &mut Option<f64>, min: f64, max: f64) {
+        if let Some(value) = value {
+            self.nudge(value, min, max);
+        }
+    }
+
+    // Integer version of `nudge`; small amounts leave most values alone
+    fn step(&mut self, value: i64, min: i64, max: i64) -> i64 {
+        self.step_span(value, max - min, min, max)
+    }
+
+    // `step` for values whose valid range is far wider than the usual one: the step size
+    // follows the usual `span`, and only the valid range clamps the result
+    fn step_span(&mut self, value: i64, span: i64, min: i64, max: i64) -> i64 {
+        if !self.chance(self.amount) {
+            return value;
+        }
+        let step = ((span as f64 * 0.25 * self.amount).round() as i64).max(1);
+        (value + self.rng.gen_range(-step..=step)).clamp(min, max)
+    }
+
+    // Occasionally switch to another of the allowed values
+    fn pick<T: Copy>(&mut self, value: T, choices: &[T]) -> T {
+        if self.chance(self.amount * 0.3) {
+            choices[self.index(choices.len())]
+        } else {
+            value
+        }
+    }
+
+    fn pick_index(&mut self, value: usize, n: usize) -> usize {
+        if self.chance(self.amount * 0.3) { self.index(n) } else { value }
+    }
+
+    fn flip(&mut self, value: &mut bool) {
+        if self.chance(self.amount * 0.2) {
+            *value = !*value;
+        }
+    }
+
+    // Occasionally add or remove one member of a subset of 0..n, never emptying it
+    fn toggle_member(&mut self, members: &mut Vec<usize>, n: usize) {
+        if !self.chance(self.amount * 0.3) {
+            return;
+        }
+        let member = self.index(n);
+        match members.iter().position(|&m| m == member) {
+            Some(position) if members.len() > 1 => {
+                members.remove(position);
+            }
+            Some(_) => {}
+            None => {
+                members.push(member);
+                members.sort_unstable();
+            }
+        }
+    }
+}
+
+// Complete effect options for a seed. Higher chaos enables more effects, makes them
+// stronger and sometimes shuffles the order they run in.
+pub fn generate(seed: u64, chaos: f64) -> GlitchOptions {
+    let mut dice = Dice::new(seed, chaos);
+    let mut options = GlitchOptions { seed: Some(seed), ..Default::default() };
+
+    let density = 0.1 + 0.35 * dice.amount;
+    for (stage, weight) in RANDOM_STAGES {
+        if dice.chance(density * weight) {
+            enable(&mut options, stage, &mut dice);
+        }
+    }
+    if job::stages(&options).is_empty() {
+        let (stage, _) = RANDOM_STAGES[dice.index(RANDOM_STAGES.len())];
+        enable(&mut options, stage, &mut dice);
+    }
+
+    if dice.chance(dice.amount * 0.5) {
+        let mut stages = job::stages(&options);
+        stages.shuffle(&mut dice.rng);
+        options.order = Some(stage_names(&stages));
+    }
+    options
+}
+
+// `generate` wrapped in a recipe, with the seed stored on the recipe
+pub fn generate_recipe(seed: u64, chaos: f64) -> Recipe {
+    let mut effects = generate(seed, chaos);
+    effects.seed = None;
+    Recipe::new(format!("Random #{seed}"), seed, effects)
+}
+
+// Perturb a recipe: nudge its parameters, toggle effects, swap neighbouring steps and
+// sometimes draw a new random seed. `amount` (0.0-1.0) scales all of these.
+pub fn mutate(recipe: &mut Recipe, amount: f64, seed: u64) {
+    let mut dice = Dice::new(seed, amount);
+    let options = &mut recipe.effects;
+
+    mutate_params(options, &mut dice);
+
+    // Toggle effects, always keeping at least one
+    for (stage, _) in RANDOM_STAGES {
+        let stages = job::stages(options);
+        if stages.contains(&stage) {
+            if stages.len() > 1 && dice.chance(dice.amount * 0.15) {
+                disable(options, stage);
+            }
+        } else if dice.chance(dice.amount * 0.1) {
+            enable(options, stage, &mut dice);
+        }
+    }
+
+    let mut stages = job::stages(options);
+    if stages.len() > 1 && dice.chance(dice.amount * 0.5) {
+        let i = dice.index(stages.len() - 1);
+        stages.swap(i, i + 1);
+        options.order = Some(stage_names(&stages));
+    }
+
+    // Without a seed the mutation would not be reproducible, so always set one then
+    if recipe.seed.or(recipe.effects.seed).is_none() || dice.chance(dice.amount * 0.3) {
+        recipe.seed = Some(dice.rng.gen_range(0..=MAX_SEED));
+    }
+}
+
+fn stage_names(stages: &[Stage]) -> Vec<String> {
+    stages.iter().map(|stage| stage.name().to_string()).collect()
+}
+
+fn enable(options: &mut GlitchOptions, stage: Stage, d: &mut Dice) {
+    match stage {
+        Stage::PixelSort => options.pixel_sort = Some(random_pixel_sort(d)),
+        Stage::DataBend => options.data_bend = Some(random_data_bend(d)),
+        Stage::ChannelShift => options.channel_shift = Some(random_channel_shift(d)),
+        Stage::Noise => options.noise = Some(d.strength() * 0.5),
+        Stage::Invert => options.invert = Some(d.subset(3)),
+        Stage::Quantize => options.quantize = Some(random_levels(d)),
+        Stage::ByteCorrupt => options.byte_corrupt = Some(random_byte_corrupt(d)),
+        Stage::ChunkSwap => options.chunk_swap = Some(random_chunk_swap(d)),
+        Stage::BinaryXor => options.binary_xor = Some(random_binary_xor(d)),
+        Stage::Sonify => options.sonify = Some(random_sonify(d)),
+        Stage::Fourier => options.fourier = Some(random_fourier(d)),
+        Stage::BitPlane => options.bit_plane = Some(random_bit_plane(d)),
+        Stage::Reinterpret => options.reinterpret = Some(random_reinterpret(d)),
+        Stage::Yuv => options.yuv = Some(random_yuv(d)),
+        Stage::ChannelChains => options.channel_chains = Some(vec![random_channel_chain(d)]),
+        Stage::ImageBlend | Stage::Layers | Stage::Transform => {}
+    }
+}
+
+fn disable(options: &mut GlitchOptions, stage: Stage) {
+    match stage {
+        Stage::PixelSort => options.pixel_sort = None,
+        Stage::DataBend => options.data_bend = None,
+        Stage::ChannelShift => options.channel_shift = None,
+        Stage::Noise => options.noise = None,
+        Stage::Invert => options.invert = None,
+        Stage::Quantize => options.quantize = None,
+        Stage::ByteCorrupt => options.byte_corrupt = None,
+        Stage::ChunkSwap => options.chunk_swap = None,
+        Stage::BinaryXor => options.binary_xor = None,
+        Stage::ImageBlend => options.image_blend = None,
+        Stage::Sonify => options.sonify = None,
+        Stage::Fourier => options.fourier = None,
+        Stage::BitPlane => options.bit_plane = None,
+        Stage::Reinterpret => options.reinterpret = None,
+        Stage::Yuv => options.yuv = None,
+        Stage::ChannelChains => {
+            options.red_channel = None;
+            options.green_channel = None;
+            options.blue_channel = None;
+            options.alpha_channel = None;
+            options.channel_chains = None;
+        }
+        Stage::Layers => options.layers = None,
+        Stage::Transform => options.transform = None,
+    }
+}
+
+// Automatic thresholds adapt to whatever image the recipe ends up on
+fn random_pixel_sort(d: &mut Dice) -> PixelSortOptions {
+    let auto_threshold = d.chance(0.4).then(|| d.index(3));
+    PixelSortOptions {
+        intensity: d.strength(),
+        threshold: d.range(0.2, 0.9),
+        vertical: d.chance(0.5),
+        channel: d.chance(0.3).then(|| d.index(3)),
+        auto_threshold,
+        auto_target: matches!(auto_threshold, Some(1 | 2)).then(|| d.range(0.2, 0.8)),
+    }
+}
+
+fn random_data_bend(d: &mut Dice) -> DataBendOptions {
+    DataBendOptions {
+        amount: d.strength() * 0.5,
+        mode: Some(d.index(4)),
+        chunk_size: Some(d.range(0.02, 0.1 + 0.4 * d.amount)),
+        channel: d.chance(0.25).then(|| d.index(3)),
+    }
+}
+
+fn random_channel_shift(d: &mut Dice) -> ChannelShiftOptions {
+    ChannelShiftOptions {
+        amount: d.strength(),
+        channels: Some(d.subset(3)),
+        direction: Some(d.offset(1)),
+    }
+}
+
+// Fewer levels, so harsher posterization, at higher chaos
+fn random_levels(d: &mut Dice) -> usize {
+    let max_levels = (32.0 - 28.0 * d.amount).round() as usize;
+    d.rng.gen_range(2..=max_levels)
+}
+
+fn random_byte_corrupt(d: &mut Dice) -> ByteCorruptOptions {
+    ByteCorruptOptions {
+        amount: d.strength(),
+        mode: Some(d.index(4)),
+        block_size: Some(1 + d.index(1 + (31.0 * d.amount) as usize)),
+        structured: d.chance(0.5),
+    }
+}
+
+fn random_chunk_swap(d: &mut Dice) -> ChunkSwapOptions {
+    ChunkSwapOptions {
+        amount: d.strength(),
+        chunk_size: Some(d.range(0.1, 1.0)),
+        preserve_alpha: true,
+    }
+}
+
+fn random_binary_xor(d: &mut Dice) -> BinaryXorOptions {
+    BinaryXorOptions {
+        pattern: None,
+        strength: d.strength(),
+        mode: Some(d.index(4)),
+    }
+}
+
+// Only the parameters the chosen effect uses are set; the rest keep their defaults
+fn random_sonify(d: &mut Dice) -> SonifyOptions {
+    let effect = d.index(9);
+    SonifyOptions {
+        effect,
+        amount: d.strength(),
+        channel: d.chance(0.3).then(|| d.index(3)),
+        interleaved: d.chance(0.3),
+        vertical: d.chance(0.5),
+        delay: matches!(effect, 0 | 1).then(|| d.range(0.1, 2.0)),
+        feedback: matches!(effect, 0 | 1 | 5).then(|| d.range(0.0, 0.3 + 0.6 * d.amount)),
+        cutoff: matches!(effect, 3 | 4).then(|| d.range(0.02, 0.6)),
+        resonance: None,
+        bits: (effect == 2).then(|| d.rng.gen_range(2..=8)),
+        downsample: (effect == 2).then(|| 1 + d.index(1 + (7.0 * d.amount) as usize)),
+        drive: (effect == 6).then(|| d.range(1.5, 2.0 + 10.0 * d.amount)),
+        rate: (effect == 5).then(|| d.range(0.1, 2.0)),
+        stretch: matches!(effect, 7 | 8).then(|| d.range(0.5, 3.0)),
+    }
+}
+
+// Magnitude and phase swaps need a secondary image, so they are left out
+const FOURIER_MODES: [usize; 5] = [0, 3, 4, 5, 6];
+
+fn random_fourier(d: &mut Dice) -> FourierOptions {
+    let mode = FOURIER_MODES[d.index(FOURIER_MODES.len())];
+    let band = matches!(mode, 3 | 4);
+    let low = d.range(0.0, 0.4);
+    FourierOptions {
+        mode,
+        amount: d.strength(),
+        channel: d.chance(0.3).then(|| d.index(3)),
+        low: band.then_some(low),
+        high: band.then(|| low + d.range(0.05, 0.6)),
+        angle: (mode == 5).then(|| d.range(-180.0, 180.0)),
+        levels: (mode == 6).then(|| 2 + d.index(7)),
+        secondary_image: None,
+        secondary_asset: None,
+    }
+}
+
+fn random_bit_plane(d: &mut Dice) -> BitPlaneOptions {
+    let operation = d.index(6);
+    BitPlaneOptions {
+        operation,
+        channels: None,
+        planes: d.chance(0.5).then(|| d.subset(8).into_iter().map(|plane| plane as u8).collect()),
+        shift: matches!(operation, 1 | 2).then(|| (1 + d.index(3)) as i32 * if d.chance(0.5) { 1 } else { -1 }),
+        swaps: (operation == 3).then(|| {
+            let count = 1 + d.index(3);
+            (0..count)
+                .map(|_| BitSwap {
+                    channel_a: d.index(3),
+                    bit_a: d.index(8) as u8,
+                    channel_b: d.index(3),
+                    bit_b: d.index(8) as u8,
+                })
+                .collect()
+        }),
+        regions: None,
+        invert_regions: false,
+    }
+}
+
+fn random_reinterpret(d: &mut Dice) -> ReinterpretOptions {
+    ReinterpretOptions {
+        format: 1 + d.index(9),
+        width_offset: Some(d.offset(1 + (8.0 * d.amount) as i32)),
+        byte_offset: Some(d.index(1 + (12.0 * d.amount) as usize)),
+    }
+}
+
+fn random_yuv(d: &mut Dice) -> YuvOptions {
+    let max_shift = 2 + (20.0 * d.amount) as i32;
+    YuvOptions {
+        subsampling: Some(d.index(4)),
+        chroma_shift_x: Some(d.offset(max_shift)),
+        chroma_shift_y: Some(d.offset(max_shift)),
+        luma_sort: d.chance(0.3).then(|| random_pixel_sort(d)),
+        planes: None,
+    }
+}
+
+// One effect on one channel of a random color space
+fn random_channel_chain(d: &mut Dice) -> ChannelChain {
+    let space = d.index(3);
+    let channel = d.index(3);
+    let mut effects = ChannelEffects::default();
+    match d.index(8) {
+        0 => effects.pixel_sort = Some(random_pixel_sort(d)),
+        1 => effects.data_bend = Some(random_data_bend(d)),
+        2 => effects.shift = Some(random_channel_shift(d)),
+        3 => effects.noise = Some(d.strength() * 0.5),
+        4 => effects.invert = Some(true),
+        5 => effects.quantize = Some(random_levels(d)),
+        6 => effects.byte_corrupt = Some(random_byte_corrupt(d)),
+        _ => effects.binary_xor = Some(random_binary_xor(d)),
+    }
+    ChannelChain { space, channel, effects }
+}
+
+fn mutate_params(options: &mut GlitchOptions, d: &mut Dice) {
+    if let Some(o) = &mut options.pixel_sort {
+        mutate_pixel_sort(o, d);
+    }
+    if let Some(o) = &mut options.data_bend {
+        mutate_data_bend(o, d);
+    }
+    if let Some(o) = &mut options.channel_shift {
+        mutate_channel_shift(o, d);
+    }
+    if let Some(noise) = &mut options.noise {
+        d.nudge(noise, 0.0, 1.0);
+    }
+    if let Some(channels) = &mut options.invert {
+        d.toggle_member(channels, 3);
+    }
+    if let Some(levels) = &mut options.quantize {
+        *levels = d.step_span(*levels as i64, 62, 2, 256) as usize;
+    }
+    if let Some(o) = &mut options.byte_corrupt {
+        mutate_byte_corrupt(o, d);
+    }
+    if let Some(o) = &mut options.chunk_swap {
+        d.nudge(&mut o.amount, 0.0, 1.0);
+        d.nudge_opt(&mut o.chunk_size, 0.01, 1.0);
+        d.flip(&mut o.preserve_alpha);
+    }
+    if let Some(o) = &mut options.binary_xor {
+        mutate_binary_xor(o, d);
+    }
+    if let Some(o) = &mut options.image_blend {
+        mutate_image_blend(o, d);
+    }
+    if let Some(o) = &mut options.sonify {
+        mutate_sonify(o, d);
+    }
+    if let Some(o) = &mut options.fourier {
+        mutate_fourier(o, d);
+    }
+    if let Some(o) = &mut options.bit_plane {
+        mutate_bit_plane(o, d);
+    }
+    if let Some(o) = &mut options.reinterpret {
+        o.format = d.pick_index(o.format, 10);
+        o.width_offset = o.width_offset.map(|offset| d.step_span(offset as i64, 128, INT_MIN, INT_MAX) as i32);
+        o.byte_offset = o.byte_offset.map(|offset| d.step_span(offset as i64, 64, 0, INT_MAX) as usize);
+    }
+    if let Some(o) = &mut options.yuv {
+        mutate_yuv(o, d);
+    }
+    let channels = [
+        &mut options.red_channel,
+        &mut options.green_channel,
+        &mut options.blue_channel,
+        &mut options.alpha_channel,
+    ];
+    for effects in channels.into_iter().flatten() {
+        mutate_channel_effects(effects, d);
+    }
+    for chain in options.channel_chains.iter_mut().flatten() {
+        mutate_channel_effects(&mut chain.effects, d);
+    }
+    for layer in options.layers.iter_mut().flatten() {
+        mutate_layer(layer, d);
+    }
+}
+
+fn mutate_pixel_sort(o: &mut PixelSortOptions, d: &mut Dice) {
+    d.nudge(&mut o.intensity, 0.0, 1.0);
+    d.nudge(&mut o.threshold, 0.0, 1.0);
+    d.flip(&mut o.vertical);
+    o.channel = d.pick(o.channel, &[None, Some(0), Some(1), Some(2)]);
+    o.auto_threshold = d.pick(o.auto_threshold, &[None, Some(0), Some(1), Some(2)]);
+    d.nudge_opt(&mut o.auto_target, 0.0, 1.0);
+}
+
+fn mutate_data_bend(o: &mut DataBendOptions, d: &mut Dice) {
+    d.nudge(&mut o.amount, 0.0, 1.0);
+    o.mode = d.pick(o.mode, &[None, Some(0), Some(1), Some(2), Some(3)]);
+    d.nudge_opt(&mut o.chunk_size, 0.01, 1.0);
+    o.channel = d.pick(o.channel, &[None, Some(0), Some(1), Some(2), Some(3)]);
+}
+
+fn mutate_channel_shift(o: &mut ChannelShiftOptions, d: &mut Dice) {
+    d.nudge(&mut o.amount, 0.0, 1.0);
+    if let Some(channels) = &mut o.channels {
+        d.toggle_member(channels, 3);
+    }
+    o.direction = d.pick(o.direction, &[None, Some(-1), Some(0), Some(1)]);
+}
+
+fn mutate_byte_corrupt(o: &mut ByteCorruptOptions, d: &mut Dice) {
+    d.nudge(&mut o.amount, 0.0, 1.0);
+    o.mode = d.pick(o.mode, &[None, Some(0), Some(1), Some(2), Some(3)]);
+    o.block_size = o.block_size.map(|size| d.step_span(size as i64, 63, 1, INT_MAX) as usize);
+    d.flip(&mut o.structured);
+}
+
+fn mutate_binary_xor(o: &mut BinaryXorOptions, d: &mut Dice) {
+    d.nudge(&mut o.strength, 0.0, 1.0);
+    o.mode = d.pick(o.mode, &[None, Some(0), Some(1), Some(2), Some(3)]);
+}
+
+fn mutate_image_blend(o: &mut ImageBlendOptions, d: &mut Dice) {
+    d.nudge(&mut o.amount, 0.0, 1.0);
+    o.blend_mode = d.pick_index(o.blend_mode, blend::MODES);
+    o.offset_x = d.step_span(o.offset_x as i64, 1000, INT_MIN, INT_MAX) as i32;
+    o.offset_y = d.step_span(o.offset_y as i64, 1000, INT_MIN, INT_MAX) as i32;
+}
+
+fn mutate_layer(o: &mut Layer, d: &mut Dice) {
+    d.nudge_opt(&mut o.opacity, 0.0, 1.0);
+    o.blend_mode = o.blend_mode.map(|mode| d.pick_index(mode, blend::MODES));
+}
+
+fn mutate_sonify(o: &mut SonifyOptions, d: &mut Dice) {
+    o.effect = d.pick_index(o.effect, 9);
+    d.nudge(&mut o.amount, 0.0, 1.0);
+    o.channel = d.pick(o.channel, &[None, Some(0), Some(1), Some(2), Some(3)]);
+    d.flip(&mut o.interleaved);
+    d.flip(&mut o.vertical);
+    d.nudge_opt(&mut o.delay, 0.0, 4.0);
+    d.nudge_opt(&mut o.feedback, 0.0, 0.95);
+    d.nudge_opt(&mut o.cutoff, 0.0, 1.0);
+    d.nudge_opt(&mut o.resonance, 0.1, 10.0);
+    o.bits = o.bits.map(|bits| d.step(bits as i64, 1, 16) as u32);
+    o.downsample = o.downsample.map(|factor| d.step_span(factor as i64, 15, 1, INT_MAX) as usize);
+    d.nudge_opt(&mut o.drive, 1.0, 20.0);
+    d.nudge_opt(&mut o.rate, 0.0, 4.0);
+    d.nudge_opt(&mut o.stretch, 0.25, 4.0);
+}
+
+fn mutate_fourier(o: &mut FourierOptions, d: &mut Dice) {
+    // Swaps stay swaps, since other modes can't switch to them without a secondary image
+    if FOURIER_MODES.contains(&o.mode) {
+        o.mode = d.pick(o.mode, &FOURIER_MODES);
+    }
+    d.nudge(&mut o.amount, 0.0, 1.0);
+    o.channel = d.pick(o.channel, &[None, Some(0), Some(1), Some(2)]);
+    d.nudge_opt(&mut o.low, 0.0, 1.0);
+    d.nudge_opt(&mut o.high, 0.0, 1.0);
+    if let (Some(low), Some(high)) = (&mut o.low, &mut o.high) {
+        if *low > *high {
+            std::mem::swap(low, high);
+        }
+    }
+    d.nudge_opt(&mut o.angle, -180.0, 180.0);
+    o.levels = o.levels.map(|levels| d.step_span(levels as i64, 14, 2, INT_MAX) as usize);
+}
+
+fn mutate_bit_plane(o: &mut BitPlaneOptions, d: &mut Dice) {
+    // Swap needs plane pairs, so only switch to it when some are set
+    let operation = d.pick_index(o.operation, 6);
+    if operation != 3 || o.swaps.is_some() {
+        o.operation = operation;
+    }
+    if let Some(planes) = &mut o.planes {
+        let mut members: Vec<usize> = planes.iter().map(|&plane| plane as usize).collect();
+        d.toggle_member(&mut members, 8);
+        *planes = members.into_iter().map(|plane| plane as u8).collect();
+    }
+    o.shift = o.shift.map(|shift| d.step(shift as i64, -7, 7) as i32);
+}
+
+fn mutate_yuv(o: &mut YuvOptions, d: &mut Dice) {
+    o.subsampling = o.subsampling.map(|mode| d.pick_index(mode, 4));
+    o.chroma_shift_x = o.chroma_shift_x.map(|shift| d.step_span(shift as i64, 200, INT_MIN, INT_MAX) as i32);
+    o.chroma_shift_y = o.chroma_shift_y.map(|shift| d.step_span(shift as i64, 200, INT_MIN, INT_MAX) as i32);
+    if let Some(sort) = &mut o.luma_sort {
+        mutate_pixel_sort(sort, d);
+    }
+    for plane in o.planes.iter_mut().flatten() {
+        mutate_params(&mut plane.effects, d);
+    }
+}
+
+fn mutate_channel_effects(effects: &mut ChannelEffects, d: &mut Dice) {
+    if let Some(o) = &mut effects.pixel_sort {
+        mutate_pixel_sort(o, d);
+    }
+    if let Some(o) = &mut effects.data_bend {
+        mutate_data_bend(o, d);
+    }
+    if let Some(o) = &mut effects.shift {
+        mutate_channel_shift(o, d);
+    }
+    if let Some(noise) = &mut effects.noise {
+        d.nudge(noise, 0.0, 1.0);
+    }
+    if let Some(invert) = &mut effects.invert {
+        d.flip(invert);
+    }
+    if let Some(levels) = &mut effects.quantize {
+        *levels = d.step_span(*levels as i64, 62, 2, 256) as usize;
+    }
+    if let Some(o) = &mut effects.byte_corrupt {
+        mutate_byte_corrupt(o, d);
+    }
+    if let Some(o) = &mut effects.binary_xor {
+        mutate_binary_xor(o, d);
+    }
+}
+
+// "Surprise me": complete effect options for a seed, ready for `apply_effects`.
+// `chaos` (0.0-1.0) sets how many effects are on and how strong they are.
+#[wasm_bindgen]
+pub fn random_options(seed: u64, chaos: f64) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&generate(seed, chaos))?)
+}
+
+// Same as `random_options`, wrapped in a recipe that can be saved, shared and mutated
+#[wasm_bindgen]
+pub fn random_recipe(seed: u64, chaos: f64) -> Result<JsValue, JsValue> {
+    recipe::to_js(&generate_recipe(seed, chaos))
+}
+
+// "More like this": a variation of a recipe (any version) or bare options. The same
+// recipe, amount and seed always give the same variation.
+#[wasm_bindgen]
+pub fn mutate_recipe(recipe_js: JsValue, amount: f64, seed: u64) -> Result<JsValue, JsValue> {
+    let mut recipe = recipe::from_js(recipe_js)?;
+    mutate(&mut recipe, amount, seed);
+    recipe::to_js(&recipe)
+}
@@ -0,0 +1,321 @@
+// Versioned recipe format for sharing presets between users, app versions and tools.
+//
+// A recipe is JSON with these top-level fields (version 1):
+//
+//   version      Format version, always written as RECIPE_VERSION
+//   name         Display name
+//   description  Free text (optional)
+//   tags         List of strings (optional)
+//   seed         Random seed so the recipe renders identically everywhere (optional)
+//   effects      The effect pipeline, exactly the options `apply_effects` takes
+//   timeline     { duration, fps, looping } for animated recipes; modulation routes and
+//                audio mappings live in `effects`. Render times snap to the nearest frame
+//                and wrap (looping) or clamp to the duration (optional)
+//   assets       Images the pipeline references: { hash, name, width, height, uri }.
+//                `hash` is the content hash from `image_hash`; `image_blend.secondary_asset`
+//                and `fourier.secondary_asset` point at it so the image is found among the
+//                registered images.
+//   shader       WebGL shader settings, stored as-is for the app (optional)
+//
+// Unset options are left out. The compact form is the JSON deflated behind a 4-byte header
+// ("GMR" and the version), and the URL-safe form is that binary in unpadded base64url.
+// Older recipes are upgraded on load by running the migrations in order; for the compact
+// form the header's version is the one migrated from.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::GlitchOptions;
+
+pub const RECIPE_VERSION: u64 = 1;
+
+const MAGIC: &[u8; 3] = b"GMR";
+
+// Upper bound for inflated recipes, so a malicious link can't exhaust memory
+const MAX_RECIPE_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Timeline {
+    pub(crate) duration: f64,         // Length in seconds
+    pub(crate) fps: Option<f64>,      // Frames per second (default: 30)
+    pub(crate) looping: Option<bool>, // Whether the animation is meant to loop
+}
+
+impl Timeline {
+    // Time of the frame shown at `time`
+    fn frame_time(&self, time: f64) -> f64 {
+        let fps = self.fps.unwrap_or(30.0);
+        if self.duration <= 0.0 || fps <= 0.0 {
+            return time;
+        }
+        let frames = (self.duration * fps).round().max(1.0);
+        let frame = (time * fps).round();
+        let frame = if self.looping.unwrap_or(false) {
+            frame.rem_euclid(frames)
+        } else {
+            frame.clamp(0.0, frames)
+        };
+        frame / fps
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Asset {
+    pub(crate) hash: Option<String>, // Content hash, missing only for migrated legacy presets
+    pub(crate) name: Option<String>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) uri: Option<String>,  // Where the app can load the image from, if known
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Recipe {
+    pub(crate) version: u64,
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    pub(crate) tags: Option<Vec<String>>,
+    pub(crate) seed: Option<u64>,
+    pub(crate) effects: GlitchOptions,
+    pub(crate) timeline: Option<Timeline>,
+    pub(crate) assets: Option<Vec<Asset>>,
+    pub(crate) shader: Option<Value>,
+}
+
+impl Recipe {
+    pub(crate) fn new(name: String, seed: u64, effects: GlitchOptions) -> Self {
+        Self {
+            version: RECIPE_VERSION,
+            name,
+            description: None,
+            tags: None,
+            seed: Some(seed),
+            effects,
+            timeline: None,
+            assets: None,
+            shader: None,
+        }
+    }
+
+    // Effect options for one frame: the recipe's seed, and the time when animated, placed
+    // on the timeline if there is one
+    pub(crate) fn into_options(mut self, time: Option<f64>) -> GlitchOptions {
+        if self.seed.is_some() {
+            self.effects.seed = self.seed;
+        }
+        if let Some(time) = time {
+            let time = self.timeline.as_ref().map_or(time, |timeline| timeline.frame_time(time));
+            self.effects.time = Some(time);
+        }
+        self.effects
+    }
+}
+
+// Each entry upgrades a recipe from version `index` to `index + 1`
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; 1] = [migrate_v0];
+
+// Bring any older recipe up to RECIPE_VERSION
+pub fn migrate(value: Value) -> Result<Value, String> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > RECIPE_VERSION {
+        return Err(format!("Recipe version {version} is newer than supported ({RECIPE_VERSION})"));
+    }
+    MIGRATIONS[version as usize..].iter().try_fold(value, |value, migration| migration(value))
+}
+
+// Version 0 is the app's original preset: `EffectPreset` from useEffectPresets.ts (or just
+// its `glitchOptions`) with camelCase keys and the blend image embedded in the options
+fn migrate_v0(value: Value) -> Result<Value, String> {
+    let Value::Object(mut preset) = value else {
+        return Err("Recipe must be a JSON object".to_string());
+    };
+    let mut options = match preset.remove("glitchOptions") {
+        Some(Value::Object(options)) => options,
+        Some(_) => return Err("glitchOptions must be an object".to_string()),
+        None => std::mem::take(&mut preset),
+    };
+
+    // The blend image becomes an asset; its pixels were never part of the saved preset
+    let mut assets = Vec::new();
+    if let Some(Value::Object(mut blend)) = options.remove("imageBlend") {
+        let image = blend.remove("secondaryImage").unwrap_or(Value::Null);
+        let settings = blend.remove("blendOptions").unwrap_or(Value::Null);
+        let mode = match settings.get("mode").and_then(Value::as_str) {
+            Some("difference") => 1,
+            Some("multiply") => 2,
+            Some("screen") => 3,
+            Some("overlay") => 4,
+            _ => 0,
+        };
+        let number = |key: &str, default: f64| settings.get(key).and_then(Value::as_f64).unwrap_or(default);
+        options.insert("image_blend".to_string(), serde_json::json!({
+            "blend_mode": mode,
+            "amount": number("amount", 0.5),
+            "offset_x": number("offsetX", 0.0) as i32,
+            "offset_y": number("offsetY", 0.0) as i32,
+        }));
+        if image.is_object() {
+            assets.push(serde_json::json!({
+                "name": image.get("name"),
+                "width": image.get("width").and_then(Value::as_u64).unwrap_or(0),
+                "height": image.get("height").and_then(Value::as_u64).unwrap_or(0),
+                "uri": image.get("dataUrl"),
+            }));
+        }
+    }
+
+    let mut recipe = Map::new();
+    recipe.insert("version".to_string(), Value::from(1));
+    recipe.insert("name".to_string(), preset.remove("name").unwrap_or_else(|| Value::from("Untitled")));
+    for key in ["description", "tags", "seed"] {
+        if let Some(value) = preset.remove(key) {
+            recipe.insert(key.to_string(), value);
+        }
+    }
+    recipe.insert("effects".to_string(), snake_case_keys(Value::Object(options)));
+    if !assets.is_empty() {
+        recipe.insert("assets".to_string(), Value::Array(assets));
+    }
+    if let Some(shader) = preset.remove("shaderOptions") {
+        recipe.insert("shader".to_string(), shader);
+    }
+    Ok(Value::Object(recipe))
+}
+
+fn snake_case_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (snake_case(&key), snake_case_keys(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(snake_case_keys).collect()),
+        value => value,
+    }
+}
+
+fn snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// Drop nulls so unset options are left out of the encoded recipe
+fn prune(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, prune(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(prune).collect()),
+        value => value,
+    }
+}
+
+pub(crate) fn to_value(recipe: &Recipe) -> Result<Value, String> {
+    serde_json::to_value(recipe).map(prune).map_err(|e| e.to_string())
+}
+
+pub(crate) fn from_value(value: Value) -> Result<Recipe, String> {
+    let mut recipe: Recipe = serde_json::from_value(migrate(value)?).map_err(|e| e.to_string())?;
+    recipe.version = RECIPE_VERSION;
+    Ok(recipe)
+}
+
+pub fn to_json(recipe: &Recipe) -> Result<String, String> {
+    serde_json::to_string_pretty(&to_value(recipe)?).map_err(|e| e.to_string())
+}
+
+pub fn to_binary(recipe: &Recipe) -> Result<Vec<u8>, String> {
+    let json = serde_json::to_vec(&to_value(recipe)?).map_err(|e| e.to_string())?;
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(RECIPE_VERSION as u8);
+    bytes.extend(miniz_oxide::deflate::compress_to_vec(&json, 9));
+    Ok(bytes)
+}
+
+pub fn from_binary(bytes: &[u8]) -> Result<Recipe, String> {
+    if bytes.len() < 4 || &bytes[..3] != MAGIC {
+        return Err("Not a GlitchMixer recipe".to_string());
+    }
+    let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&bytes[4..], MAX_RECIPE_BYTES)
+        .map_err(|e| format!("Corrupt recipe data: {e:?}"))?;
+    let mut value: Value = serde_json::from_slice(&json).map_err(|e| e.to_string())?;
+    // The header's version decides which migrations run
+    if let Value::Object(recipe) = &mut value {
+        recipe.insert("version".to_string(), Value::from(bytes[3]));
+    }
+    from_value(value)
+}
+
+// JSON (any version) or the URL-safe compact form
+pub fn parse(input: &str) -> Result<Recipe, String> {
+    let input = input.trim();
+    if input.starts_with('{') {
+        from_value(serde_json::from_str(input).map_err(|e| e.to_string())?)
+    } else {
+        let bytes = URL_SAFE_NO_PAD.decode(input).map_err(|e| format!("Invalid recipe link: {e}"))?;
+        from_binary(&bytes)
+    }
+}
+
+pub(crate) fn from_js(recipe_js: JsValue) -> Result<Recipe, JsValue> {
+    // Go through serde_json so JS objects get the same migrations as text
+    let value: Value = serde_wasm_bindgen::from_value(recipe_js)?;
+    Ok(from_value(value)?)
+}
+
+pub(crate) fn to_js(recipe: &Recipe) -> Result<JsValue, JsValue> {
+    let value = to_value(recipe)?;
+    Ok(value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+// Parse a recipe from JSON (any version) or a compact link and return it as a current-version object
+#[wasm_bindgen]
+pub fn parse_recipe(input: &str) -> Result<JsValue, JsValue> {
+    to_js(&parse(input)?)
+}
+
+// Upgrade a recipe object (e.g. a stored legacy preset) to the current version
+#[wasm_bindgen]
+pub fn migrate_recipe(recipe_js: JsValue) -> Result<JsValue, JsValue> {
+    to_js(&from_js(recipe_js)?)
+}
+
+#[wasm_bindgen]
+pub fn recipe_to_json(recipe_js: JsValue) -> Result<String, JsValue> {
+    Ok(to_json(&from_js(recipe_js)?)?)
+}
+
+#[wasm_bindgen]
+pub fn recipe_to_binary(recipe_js: JsValue) -> Result<Vec<u8>, JsValue> {
+    Ok(to_binary(&from_js(recipe_js)?)?)
+}
+
+#[wasm_bindgen]
+pub fn recipe_from_binary(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    to_js(&from_binary(bytes)?)
+}
+
+// Compact URL-safe string for share links
+#[wasm_bindgen]
+pub fn recipe_to_url(recipe_js: JsValue) -> Result<String, JsValue> {
+    Ok(URL_SAFE_NO_PAD.encode(to_binary(&from_js(recipe_js)?)?))
+}
+
+// Content hash for an image, to fill in a recipe asset before the image is registered
+#[wasm_bindgen]
+pub fn hash_image(data: &[u8], width: u32, height: u32) -> String {
+    crate::buffer::image_hash(data, width, height)
+}
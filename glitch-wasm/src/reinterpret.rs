@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::yuv::ycbcr_to_rgb;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReinterpretOptions {
+    pub(crate) format: usize,             // 0=RGBA, 1=RGB24, 2=BGR24, 3=BGRA, 4=RGB565, 5=YUV420 planar, 6=NV12, 7=gray 8-bit, 8=gray 16-bit LE, 9=gray 16-bit BE (16-bit formats keep only the high byte of each sample)
+    pub(crate) width_offset: Option<i32>, // Read with the row width off by this many pixels (default: 0)
+    pub(crate) byte_offset: Option<usize>, // Skip this many bytes before decoding; whole pixels scale with the image (default: 0)
+}
+
+// Bytes per pixel of a format (the luma plane for the YUV formats)
+fn bytes_per_pixel(format: usize) -> usize {
+    match format {
+        0 | 3 => 4,
+        1 | 2 => 3,
+        4 | 8 | 9 => 2,
+        _ => 1,
+    }
+}
+
+// Decode pixel `k` of the stream as if it were an image of `stride` pixels per row
+fn decode_pixel(stream: &[u8], format: usize, k: usize, stride: usize, height: usize) -> [u8; 4] {
+    let byte = |i: usize| stream.get(i).copied();
+    let packed = |i: usize, n: usize| -> Option<&[u8]> { stream.get(i * n..i * n + n) };
+
+    let pixel = match format {
+        0 => packed(k, 4).map(|p| [p[0], p[1], p[2], p[3]]),
+        1 => packed(k, 3).map(|p| [p[0], p[1], p[2], 255]),
+        2 => packed(k, 3).map(|p| [p[2], p[1], p[0], 255]),
+        3 => packed(k, 4).map(|p| [p[2], p[1], p[0], p[3]]),
+        4 => packed(k, 2).map(|p| {
+            let value = u16::from_le_bytes([p[0], p[1]]);
+            let r = ((value >> 11) & 0x1F) as u8;
+            let g = ((value >> 5) & 0x3F) as u8;
+            let b = (value & 0x1F) as u8;
+            [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+        }),
+        5 | 6 => {
+            let x = k % stride;
+            let y = k / stride;
+            let luma_size = stride * height;
+            let chroma_stride = stride.div_ceil(2);
+            let chroma_index = (y / 2) * chroma_stride + x / 2;
+            let (u, v) = if format == 5 {
+                // I420: full Y plane, then quarter-size U and V planes
+                let chroma_size = chroma_stride * height.div_ceil(2);
+                (byte(luma_size + chroma_index), byte(luma_size + chroma_size + chroma_index))
+            } else {
+                // NV12: full Y plane, then interleaved UV pairs
+                (byte(luma_size + chroma_index * 2), byte(luma_size + chroma_index * 2 + 1))
+            };
+            byte(k).map(|luma| {
+                let [r, g, b] = ycbcr_to_rgb(luma, u.unwrap_or(128), v.unwrap_or(128));
+                [r, g, b, 255]
+            })
+        }
+        7 => byte(k).map(|g| [g, g, g, 255]),
+        8 => packed(k, 2).map(|p| [p[1], p[1], p[1], 255]), // High byte of little-endian samples
+        9 => packed(k, 2).map(|p| [p[0], p[0], p[0], 255]), // High byte of big-endian samples
+        _ => None,
+    };
+
+    // Reads past the end of the data come out black, like a truncated file
+    pixel.unwrap_or([0, 0, 0, 255])
+}
+
+// Read the RGBA buffer as a different pixel layout and/or row width, converting back to RGBA
+pub fn reinterpret(data: &mut [u8], width: u32, options: &ReinterpretOptions, scale: f64) {
+    let width = width as usize;
+    if width == 0 || options.format > 9 { return; }
+    let height = data.len() / 4 / width;
+
+    let width_offset = (options.width_offset.unwrap_or(0) as f64 * scale).round() as i64;
+    let stride = (width as i64 + width_offset).max(1) as usize;
+    // Scale the whole pixels of the offset but keep the bytes left over, so a misaligned
+    // offset stays misaligned by the same amount at any size
+    let bpp = bytes_per_pixel(options.format);
+    let byte_offset = options.byte_offset.unwrap_or(0);
+    let pixels = (byte_offset / bpp) as f64 * scale;
+    let offset = ((pixels.round() as usize) * bpp + byte_offset % bpp).min(data.len());
+    let stream = data[offset..].to_vec();
+
+    for y in 0..height {
+        for x in 0..width {
+            // A wrong stride means each displayed row starts at a different point in the stream
+            let k = y * stride + x;
+            let idx = (y * width + x) * 4;
+            data[idx..idx + 4].copy_from_slice(&decode_pixel(&stream, options.format, k, stride, height));
+        }
+    }
+}
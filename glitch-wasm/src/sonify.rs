@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SonifyOptions {
+    pub(crate) effect: usize,            // 0=echo, 1=reverb, 2=bitcrush, 3=low-pass, 4=high-pass, 5=phaser, 6=distortion, 7=pitch shift, 8=time stretch
+    pub(crate) amount: f64,              // Wet/dry mix (0.0-1.0)
+    pub(crate) channel: Option<usize>,   // 0=R, 1=G, 2=B, 3=A, None=all
+    pub(crate) interleaved: bool,        // Treat the selected channels as one interleaved signal instead of one signal each
+    pub(crate) vertical: bool,           // Read the image column by column instead of row by row
+    pub(crate) delay: Option<f64>,       // Echo delay in rows / reverb room size (default: 0.5 / 1.0)
+    pub(crate) feedback: Option<f64>,    // Echo, reverb and phaser feedback (0.0-0.95)
+    pub(crate) cutoff: Option<f64>,      // Filter cutoff relative to the Nyquist frequency (0.0-1.0)
+    pub(crate) resonance: Option<f64>,   // Filter Q (default: 0.707)
+    pub(crate) bits: Option<u32>,        // Bitcrush depth (1-16, default: 3)
+    pub(crate) downsample: Option<usize>, // Bitcrush sample-and-hold factor (default: 1)
+    pub(crate) drive: Option<f64>,       // Distortion gain (default: 4.0)
+    pub(crate) rate: Option<f64>,        // Phaser sweeps per row (default: 0.5)
+    pub(crate) stretch: Option<f64>,     // Pitch ratio or time stretch factor (default: 2.0)
+}
+
+// Byte <-> sample conversion around the 0.0 midpoint
+fn to_sample(byte: u8) -> f64 {
+    byte as f64 / 127.5 - 1.0
+}
+
+fn to_byte(sample: f64) -> u8 {
+    ((sample + 1.0) * 127.5).round().clamp(0.0, 255.0) as u8
+}
+
+fn echo(signal: &[f64], delay: usize, feedback: f64) -> Vec<f64> {
+    let mut out = signal.to_vec();
+    if delay == 0 { return out; }
+    for i in delay..out.len() {
+        out[i] += feedback * out[i - delay];
+    }
+    out
+}
+
+// Schroeder reverb: parallel feedback combs into series all-pass filters
+fn reverb(signal: &[f64], size: f64, feedback: f64) -> Vec<f64> {
+    let comb_delays = [1557, 1617, 1491, 1422];
+    let allpass_delays = [225, 556];
+    let scale = |d: usize| ((d as f64 * size) as usize).max(1);
+
+    let mut out = vec![0.0; signal.len()];
+    for &delay in &comb_delays {
+        let delay = scale(delay);
+        let mut comb = vec![0.0; signal.len()];
+        for i in 0..signal.len() {
+            let delayed = if i >= delay { comb[i - delay] } else { 0.0 };
+            comb[i] = signal[i] + feedback * delayed;
+            out[i] += comb[i] / comb_delays.len() as f64;
+        }
+    }
+
+    for &delay in &allpass_delays {
+        let delay = scale(delay);
+        let input = out.clone();
+        for i in 0..out.len() {
+            let delayed_in = if i >= delay { input[i - delay] } else { 0.0 };
+            let delayed_out = if i >= delay { out[i - delay] } else { 0.0 };
+            out[i] = -0.5 * input[i] + delayed_in + 0.5 * delayed_out;
+        }
+    }
+    out
+}
+
+fn bitcrush(signal: &[f64], bits: u32, downsample: usize) -> Vec<f64> {
+    let levels = ((1u32 << bits.clamp(1, 16)) - 1) as f64;
+    let downsample = downsample.max(1);
+    let mut out = Vec::with_capacity(signal.len());
+    let mut held = 0.0;
+    for (i, &sample) in signal.iter().enumerate() {
+        if i % downsample == 0 {
+            held = (((sample + 1.0) * 0.5 * levels).round() / levels) * 2.0 - 1.0;
+        }
+        out.push(held);
+    }
+    out
+}
+
+// RBJ cookbook biquad low-pass / high-pass
+fn biquad(signal: &[f64], cutoff: f64, q: f64, high_pass: bool) -> Vec<f64> {
+    let w0 = std::f64::consts::PI * cutoff.clamp(0.001, 0.999);
+    let alpha = w0.sin() / (2.0 * q.max(0.1));
+    let cos = w0.cos();
+    let (b0, b1, b2) = if high_pass {
+        ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0)
+    } else {
+        ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0)
+    };
+    let (a0, a1, a2) = (1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+
+    let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+    signal.iter().map(|&x| {
+        let y = (b0 * x + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2) / a0;
+        x2 = x1;
+        x1 = x;
+        y2 = y1;
+        y1 = y;
+        y
+    }).collect()
+}
+
+// Four first-order all-pass stages swept by a sine LFO, mixed with the dry signal
+fn phaser(signal: &[f64], rate: f64, feedback: f64, row_len: usize) -> Vec<f64> {
+    let mut states = [0.0f64; 4];
+    let mut last = 0.0;
+    signal.iter().enumerate().map(|(i, &x)| {
+        let lfo = (std::f64::consts::TAU * rate * i as f64 / row_len.max(1) as f64).sin();
+        let coefficient = 0.3 + 0.6 * (lfo * 0.5 + 0.5);
+        let mut y = x + feedback * last;
+        for state in states.iter_mut() {
+            let out = -coefficient * y + *state;
+            *state = y + coefficient * out;
+            y = out;
+        }
+        last = y;
+        (x + y) * 0.5
+    }).collect()
+}
+
+fn distortion(signal: &[f64], drive: f64) -> Vec<f64> {
+    let drive = drive.max(0.01);
+    let norm = drive.tanh();
+    signal.iter().map(|&x| (x * drive).tanh() / norm).collect()
+}
+
+fn sample_linear(signal: &[f64], position: f64) -> f64 {
+    let len = signal.len();
+    let i = position.floor();
+    let t = position - i;
+    let a = signal[(i as usize) % len];
+    let b = signal[(i as usize + 1) % len];
+    a + (b - a) * t
+}
+
+// Resample by the pitch ratio, wrapping around so the length is preserved
+fn pitch_shift(signal: &[f64], ratio: f64) -> Vec<f64> {
+    let ratio = ratio.max(0.01);
+    (0..signal.len()).map(|i| sample_linear(signal, i as f64 * ratio)).collect()
+}
+
+// Granular time stretch: grains keep their pitch but are read from a slower/faster position
+fn time_stretch(signal: &[f64], factor: f64, grain: usize) -> Vec<f64> {
+    let factor = factor.max(0.01);
+    let grain = grain.max(4);
+    let hop = grain / 2;
+    let mut out = vec![0.0; signal.len()];
+    let mut weight = vec![0.0; signal.len()];
+
+    let mut start = 0;
+    while start < signal.len() {
+        let source = start as f64 / factor;
+        for k in 0..grain {
+            let i = start + k;
+            if i >= signal.len() { break; }
+            // Hann window so overlapping grains cross-fade
+            let w = 0.5 - 0.5 * (std::f64::consts::TAU * k as f64 / grain as f64).cos();
+            out[i] += sample_linear(signal, source + k as f64) * w;
+            weight[i] += w;
+        }
+        start += hop;
+    }
+
+    for (value, w) in out.iter_mut().zip(weight) {
+        if w > 1e-6 { *value /= w; }
+    }
+    out
+}
+
+// Sample-based lengths follow the spatial scale, and cutoffs shrink as it grows, so the
+// result keeps its look at any resolution
+fn process_signal(signal: &[f64], row_len: usize, options: &SonifyOptions, scale: f64) -> Vec<f64> {
+    let feedback = options.feedback.unwrap_or(0.5).clamp(0.0, 0.95);
+    let cutoff = options.cutoff.unwrap_or(0.1) / scale.max(f64::EPSILON);
+    let downsample = (options.downsample.unwrap_or(1) as f64 * scale).round() as usize;
+    match options.effect {
+        0 => echo(signal, (options.delay.unwrap_or(0.5) * row_len as f64) as usize, feedback),
+        1 => reverb(signal, options.delay.unwrap_or(1.0).max(0.01) * scale, feedback),
+        2 => bitcrush(signal, options.bits.unwrap_or(3), downsample),
+        3 => biquad(signal, cutoff, options.resonance.unwrap_or(0.707), false),
+        4 => biquad(signal, cutoff, options.resonance.unwrap_or(0.707), true),
+        5 => phaser(signal, options.rate.unwrap_or(0.5), feedback, row_len),
+        6 => distortion(signal, options.drive.unwrap_or(4.0)),
+        7 => pitch_shift(signal, options.stretch.unwrap_or(2.0)),
+        8 => time_stretch(signal, options.stretch.unwrap_or(2.0), (row_len / 8).max(64)),
+        _ => signal.to_vec(),
+    }
+}
+
+// Treat the image bytes as audio, run the selected effect and write the result back
+pub fn sonify(data: &mut [u8], width: u32, options: &SonifyOptions, scale: f64) {
+    let width = width as usize;
+    if width == 0 { return; }
+    let height = data.len() / 4 / width;
+    if height == 0 { return; }
+
+    let channels = match options.channel {
+        Some(ch) if ch < 4 => vec![ch],
+        _ => vec![0, 1, 2, 3],
+    };
+
+    // Pixel indices in reading order
+    let pixels: Vec<usize> = if options.vertical {
+        (0..width).flat_map(|x| (0..height).map(move |y| y * width + x)).collect()
+    } else {
+        (0..width * height).collect()
+    };
+    let line_len = if options.vertical { height } else { width };
+
+    // Each signal is a list of byte offsets into the image
+    let signals: Vec<Vec<usize>> = if options.interleaved {
+        vec![pixels.iter().flat_map(|&p| channels.iter().map(move |&ch| p * 4 + ch)).collect()]
+    } else {
+        channels.iter().map(|&ch| pixels.iter().map(|&p| p * 4 + ch).collect()).collect()
+    };
+    let row_len = if options.interleaved { line_len * channels.len() } else { line_len };
+
+    let amount = options.amount.clamp(0.0, 1.0);
+    for offsets in signals {
+        let dry: Vec<f64> = offsets.iter().map(|&i| to_sample(data[i])).collect();
+        let wet = process_signal(&dry, row_len, options, scale);
+        for ((&i, &d), &w) in offsets.iter().zip(&dry).zip(&wet) {
+            data[i] = to_byte(d * (1.0 - amount) + w * amount);
+        }
+    }
+}
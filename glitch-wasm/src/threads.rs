@@ -0,0 +1,71 @@
+// Rayon thread pool for the browser. Each pool thread is a Web Worker running its own
+// instance of this module on the same shared memory; the workers are started from
+// src/workerHelpers.js and then pick up rayon's thread builders over a channel.
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use js_sys::Promise;
+use rayon::ThreadBuilder;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(module = "/src/workerHelpers.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = startWorkers)]
+    fn start_workers(module: JsValue, memory: JsValue, builder: PoolBuilder) -> Promise;
+}
+
+#[wasm_bindgen]
+pub struct PoolBuilder {
+    num_threads: usize,
+    sender: Sender<ThreadBuilder>,
+    receiver: &'static Mutex<Receiver<ThreadBuilder>>, // Shared by the workers, each takes one builder
+}
+
+#[wasm_bindgen]
+impl PoolBuilder {
+    fn new(num_threads: usize) -> Self {
+        // Leaked so it stays valid however long the workers take to get to it
+        let (sender, receiver) = channel();
+        Self { num_threads, sender, receiver: Box::leak(Box::new(Mutex::new(receiver))) }
+    }
+
+    #[wasm_bindgen(js_name = numThreads)]
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    // Address of the receiver, passed to the workers in their init message
+    pub fn receiver(&self) -> *const Mutex<Receiver<ThreadBuilder>> {
+        self.receiver
+    }
+
+    // Build the global pool once all workers are waiting on the receiver
+    pub fn build(&mut self) {
+        let sender = self.sender.clone();
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.num_threads)
+            .spawn_handler(move |thread| {
+                sender.send(thread).unwrap_throw();
+                Ok(())
+            })
+            .build_global()
+            .unwrap_throw();
+    }
+}
+
+// Start `num_threads` workers and make them the global rayon pool. Only works on a cross-origin
+// isolated page (for SharedArrayBuffer) with a build that has atomics, see the README.
+#[wasm_bindgen(js_name = initThreadPool)]
+pub fn init_thread_pool(num_threads: usize) -> Promise {
+    start_workers(wasm_bindgen::module(), wasm_bindgen::memory(), PoolBuilder::new(num_threads.max(1)))
+}
+
+// Entry point of a pool worker, called by src/workerHelpers.js. Blocks the worker for good.
+#[wasm_bindgen]
+#[doc(hidden)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wbg_rayon_start_worker(receiver: *const Mutex<Receiver<ThreadBuilder>>) {
+    let receiver = unsafe { &*receiver };
+    let thread = receiver.lock().unwrap_throw().recv().unwrap_throw();
+    thread.run()
+}
@@ -0,0 +1,30 @@
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::modulation::hash64;
+
+// Pixels per tile for effects that don't care about image rows. Fixed so that tile
+// boundaries (and therefore per-tile random streams) never depend on the thread count.
+pub const TILE_PIXELS: usize = 16384;
+
+// Run `f` on consecutive chunks of `chunk_len` bytes with their index. With the `parallel`
+// feature the chunks are processed on the rayon thread pool, otherwise in order.
+pub fn for_each_chunk<F>(data: &mut [u8], chunk_len: usize, f: F)
+where
+    F: Fn(usize, &mut [u8]) + Send + Sync,
+{
+    let chunk_len = chunk_len.max(4);
+
+    #[cfg(feature = "parallel")]
+    data.par_chunks_mut(chunk_len).enumerate().for_each(|(index, chunk)| f(index, chunk));
+
+    #[cfg(not(feature = "parallel"))]
+    data.chunks_mut(chunk_len).enumerate().for_each(|(index, chunk)| f(index, chunk));
+}
+
+// Independent random stream for one chunk, derived from a per-call seed
+pub fn chunk_rng(seed: u64, index: usize) -> SmallRng {
+    SmallRng::seed_from_u64(hash64(seed ^ hash64(index as u64)))
+}
@@ -0,0 +1,422 @@
+// Resampling and geometric transforms. Filters: 0=nearest, 1=bilinear, 2=bicubic
+// (Catmull-Rom), 3=Lanczos (3 lobes). Interpolation is premultiplied, so transparent pixels
+// don't bleed their color into their neighbors.
+//
+// Every canvas-sized transform (crop, flip, rotation, zoom, affine, perspective) is a 3x3
+// projective matrix. They are chained into one, inverted, and each output pixel looks up its
+// source position once, so stacked transforms are resampled a single time. Resizing filters
+// separably and widens the kernel when shrinking, so downscales don't alias.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::ImageData;
+
+type Matrix = [[f64; 3]; 3];
+
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+// What lies outside the source image
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Edge {
+    Transparent, // Nothing outside; inside, edge pixels extend to the border when filtering
+    Clamp,
+    Tile,
+    Mirror,
+}
+
+impl Edge {
+    pub(crate) fn from_index(index: usize) -> Self {
+        match index {
+            1 => Edge::Clamp,
+            2 => Edge::Tile,
+            3 => Edge::Mirror,
+            _ => Edge::Transparent,
+        }
+    }
+
+    fn wrap(self, i: i64, n: i64) -> Option<usize> {
+        match self {
+            Edge::Transparent => (0..n).contains(&i).then_some(i as usize),
+            Edge::Clamp => Some(i.clamp(0, n - 1) as usize),
+            Edge::Tile => Some(i.rem_euclid(n) as usize),
+            Edge::Mirror => {
+                let m = i.rem_euclid(2 * n);
+                Some(if m >= n { 2 * n - 1 - m } else { m } as usize)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransformOptions {
+    pub(crate) filter: Option<usize>,          // 0=nearest, 1=bilinear, 2=bicubic, 3=Lanczos (default: 1)
+    pub(crate) edge: Option<usize>,            // Outside the source: 0=transparent, 1=clamp, 2=tile, 3=mirror (default: 0)
+    pub(crate) crop: Option<[f64; 4]>,         // x, y, width, height as fractions of the image; the crop fills the canvas
+    pub(crate) flip_x: Option<bool>,
+    pub(crate) flip_y: Option<bool>,
+    pub(crate) rotation: Option<f64>,          // Degrees clockwise around the center
+    pub(crate) zoom: Option<f64>,              // Scale around the center (default: 1)
+    pub(crate) affine: Option<[f64; 6]>,       // a, b, c, d, e, f: x' = a*x + b*y + e, y' = c*x + d*y + f, with e and f in pixels (scaled with the image)
+    pub(crate) perspective: Option<[[f64; 2]; 4]>, // Where the top-left, top-right, bottom-right and bottom-left corners go, as fractions of the image
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+fn invert(m: &Matrix) -> Option<Matrix> {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+    let det = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    Some(adjugate.map(|row| row.map(|value| value / det)))
+}
+
+fn translate(x: f64, y: f64) -> Matrix {
+    [[1.0, 0.0, x], [0.0, 1.0, y], [0.0, 0.0, 1.0]]
+}
+
+fn scaling(x: f64, y: f64) -> Matrix {
+    [[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, 1.0]]
+}
+
+// Clockwise on screen, where y points down
+fn rotation(degrees: f64) -> Matrix {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    [[cos, -sin, 0.0], [sin, cos, 0.0], [0.0, 0.0, 1.0]]
+}
+
+// Homography taking the unit square's corners (clockwise from the top left) to `corners`
+fn square_to_quad(corners: &[[f64; 2]; 4]) -> Matrix {
+    let [[x0, y0], [x1, y1], [x2, y2], [x3, y3]] = *corners;
+    let (sx, sy) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+    if sx.abs() < 1e-12 && sy.abs() < 1e-12 {
+        return [[x1 - x0, x3 - x0, x0], [y1 - y0, y3 - y0, y0], [0.0, 0.0, 1.0]];
+    }
+    let (dx1, dy1, dx2, dy2) = (x1 - x2, y1 - y2, x3 - x2, y3 - y2);
+    let det = dx1 * dy2 - dx2 * dy1;
+    let g = (sx * dy2 - dx2 * sy) / det;
+    let h = (dx1 * sy - sx * dy1) / det;
+    [
+        [x1 - x0 + g * x1, x3 - x0 + h * x3, x0],
+        [y1 - y0 + g * y1, y3 - y0 + h * y3, y0],
+        [g, h, 1.0],
+    ]
+}
+
+// Forward matrix of the options, from source to output pixel coordinates. The steps apply
+// in the order the fields are listed.
+fn forward(options: &TransformOptions, width: f64, height: f64, scale: f64) -> Matrix {
+    let mut m = IDENTITY;
+    let mut then = |step: Matrix| m = multiply(&step, &m);
+    if let Some([x, y, w, h]) = options.crop {
+        then(translate(-x * width, -y * height));
+        then(scaling(1.0 / w.max(1e-3), 1.0 / h.max(1e-3)));
+    }
+    if options.flip_x.unwrap_or(false) {
+        then([[-1.0, 0.0, width], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+    }
+    if options.flip_y.unwrap_or(false) {
+        then([[1.0, 0.0, 0.0], [0.0, -1.0, height], [0.0, 0.0, 1.0]]);
+    }
+    let (degrees, zoom) = (options.rotation.unwrap_or(0.0), options.zoom.unwrap_or(1.0).max(1e-3));
+    if degrees != 0.0 || zoom != 1.0 {
+        then(translate(-width / 2.0, -height / 2.0));
+        then(multiply(&rotation(degrees), &scaling(zoom, zoom)));
+        then(translate(width / 2.0, height / 2.0));
+    }
+    if let Some([a, b, c, d, e, f]) = options.affine {
+        then([[a, b, e * scale], [c, d, f * scale], [0.0, 0.0, 1.0]]);
+    }
+    if let Some(corners) = &options.perspective {
+        then(scaling(1.0 / width, 1.0 / height));
+        then(square_to_quad(corners));
+        then(scaling(width, height));
+    }
+    m
+}
+
+fn kernel(filter: usize, x: f64) -> f64 {
+    let x = x.abs();
+    match filter {
+        1 => (1.0 - x).max(0.0),
+        2 => {
+            // Catmull-Rom (a = -0.5)
+            if x < 1.0 {
+                (1.5 * x - 2.5) * x * x + 1.0
+            } else if x < 2.0 {
+                ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+            } else {
+                0.0
+            }
+        }
+        _ => {
+            if x < 1e-9 {
+                1.0
+            } else if x < 3.0 {
+                let px = std::f64::consts::PI * x;
+                3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+fn support(filter: usize) -> f64 {
+    match filter {
+        1 => 1.0,
+        2 => 2.0,
+        _ => 3.0,
+    }
+}
+
+fn texel(data: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+    let idx = (y * width + x) * 4;
+    data[idx..idx + 4].try_into().unwrap()
+}
+
+// Back from premultiplied sums to a straight-alpha pixel
+fn unpremultiply(sum: [f64; 4], total: f64) -> [u8; 4] {
+    if sum[3] <= 0.0 || total <= 0.0 {
+        return [0; 4];
+    }
+    let alpha = sum[3] / total;
+    let byte = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+    [byte(sum[0] / sum[3]), byte(sum[1] / sum[3]), byte(sum[2] / sum[3]), byte(alpha)]
+}
+
+// Sample `data` at continuous coordinates (pixel centers at +0.5)
+pub(crate) fn sample(data: &[u8], width: usize, height: usize, u: f64, v: f64, filter: usize, edge: Edge) -> [u8; 4] {
+    let (w, h) = (width as i64, height as i64);
+    if edge == Edge::Transparent && (u < 0.0 || v < 0.0 || u >= width as f64 || v >= height as f64) {
+        return [0; 4];
+    }
+    let edge = if edge == Edge::Transparent { Edge::Clamp } else { edge };
+    if filter == 0 {
+        return match (edge.wrap(u.floor() as i64, w), edge.wrap(v.floor() as i64, h)) {
+            (Some(x), Some(y)) => texel(data, width, x, y),
+            _ => [0; 4],
+        };
+    }
+
+    let radius = support(filter);
+    let (u, v) = (u - 0.5, v - 0.5);
+    let (x0, y0) = ((u - radius).floor() as i64 + 1, (v - radius).floor() as i64 + 1);
+    let taps = 2 * radius as i64;
+    let mut sum = [0.0; 4];
+    let mut total = 0.0;
+    for ty in y0..y0 + taps {
+        let wy = kernel(filter, v - ty as f64);
+        let Some(y) = edge.wrap(ty, h) else { continue };
+        for tx in x0..x0 + taps {
+            let weight = wy * kernel(filter, u - tx as f64);
+            let Some(x) = edge.wrap(tx, w) else { continue };
+            let pixel = texel(data, width, x, y);
+            let alpha_weight = weight * pixel[3] as f64;
+            for c in 0..3 {
+                sum[c] += pixel[c] as f64 * alpha_weight;
+            }
+            sum[3] += alpha_weight;
+            total += weight;
+        }
+    }
+    unpremultiply(sum, total)
+}
+
+// Output pixels from source positions; `map` gives the source position of an output pixel
+// center, or None where nothing maps there
+#[allow(clippy::too_many_arguments)]
+fn resample(data: &[u8], width: usize, height: usize, out_width: usize, out_height: usize,
+            filter: usize, edge: Edge, map: impl Fn(f64, f64) -> Option<(f64, f64)>) -> Vec<u8> {
+    let mut out = vec![0; out_width * out_height * 4];
+    for (y, row) in out.chunks_exact_mut(out_width * 4).enumerate() {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            if let Some((u, v)) = map(x as f64 + 0.5, y as f64 + 0.5) {
+                pixel.copy_from_slice(&sample(data, width, height, u, v, filter, edge));
+            }
+        }
+    }
+    out
+}
+
+// Apply a forward matrix to an image of the same size
+fn warp(data: &[u8], width: usize, height: usize, matrix: &Matrix, filter: usize, edge: Edge) -> Vec<u8> {
+    let Some(inverse) = invert(matrix) else { return vec![0; data.len()] };
+    resample(data, width, height, width, height, filter, edge, |x, y| {
+        let w = inverse[2][0] * x + inverse[2][1] * y + inverse[2][2];
+        // Points behind the horizon of a perspective map to nothing
+        (w > 1e-9).then(|| (
+            (inverse[0][0] * x + inverse[0][1] * y + inverse[0][2]) / w,
+            (inverse[1][0] * x + inverse[1][1] * y + inverse[1][2]) / w,
+        ))
+    })
+}
+
+// Transform the image in place, keeping its size
+pub fn transform(data: &mut [u8], width: u32, options: &TransformOptions, scale: f64) {
+    let width = width as usize;
+    if width == 0 { return; }
+    let height = data.len() / 4 / width;
+    let matrix = forward(options, width as f64, height as f64, scale);
+    if matrix == IDENTITY { return; }
+    let filter = options.filter.unwrap_or(1).min(3);
+    let edge = Edge::from_index(options.edge.unwrap_or(0));
+    let out = warp(&data[..width * height * 4], width, height, &matrix, filter, edge);
+    data[..out.len()].copy_from_slice(&out);
+}
+
+// Contributions of source samples to each output sample along one axis, widened when
+// shrinking so every source sample is covered
+fn axis_weights(src: usize, dst: usize, filter: usize) -> Vec<(usize, Vec<f64>)> {
+    let ratio = src as f64 / dst as f64;
+    let stretch = ratio.max(1.0);
+    let radius = support(filter) * stretch;
+    (0..dst).map(|i| {
+        let center = (i as f64 + 0.5) * ratio;
+        let start = (center - radius).floor().max(0.0) as usize;
+        let end = ((center + radius).ceil() as usize).min(src);
+        let weights = (start..end).map(|j| kernel(filter, (j as f64 + 0.5 - center) / stretch)).collect();
+        (start, weights)
+    }).collect()
+}
+
+// Resize to `new_width` x `new_height`
+pub fn resize(data: &[u8], width: usize, height: usize, new_width: usize, new_height: usize, filter: usize) -> Vec<u8> {
+    if filter == 0 {
+        let (sx, sy) = (width as f64 / new_width as f64, height as f64 / new_height as f64);
+        return resample(data, width, height, new_width, new_height, 0, Edge::Clamp, |x, y| Some((x * sx, y * sy)));
+    }
+
+    // Premultiplied, one axis at a time
+    let premultiplied: Vec<f64> = data.chunks_exact(4)
+        .flat_map(|p| {
+            let a = p[3] as f64;
+            [p[0] as f64 * a, p[1] as f64 * a, p[2] as f64 * a, a]
+        })
+        .collect();
+    let columns = axis_weights(width, new_width, filter);
+    let mut horizontal = vec![0.0; new_width * height * 4];
+    for y in 0..height {
+        for (x, (start, weights)) in columns.iter().enumerate() {
+            let total: f64 = weights.iter().sum();
+            for (k, weight) in weights.iter().enumerate() {
+                let src = (y * width + start + k) * 4;
+                for c in 0..4 {
+                    horizontal[(y * new_width + x) * 4 + c] += premultiplied[src + c] * weight / total;
+                }
+            }
+        }
+    }
+    let rows = axis_weights(height, new_height, filter);
+    let mut out = vec![0; new_width * new_height * 4];
+    for (y, (start, weights)) in rows.iter().enumerate() {
+        let total: f64 = weights.iter().sum();
+        for x in 0..new_width {
+            let mut sum = [0.0; 4];
+            for (k, weight) in weights.iter().enumerate() {
+                let src = ((start + k) * new_width + x) * 4;
+                for c in 0..4 {
+                    sum[c] += horizontal[src + c] * weight / total;
+                }
+            }
+            let dst = (y * new_width + x) * 4;
+            out[dst..dst + 4].copy_from_slice(&unpremultiply([sum[0], sum[1], sum[2], sum[3].max(0.0)], 1.0));
+        }
+    }
+    out
+}
+
+fn image_data(data: &[u8], width: usize, height: usize) -> Result<ImageData, JsValue> {
+    ImageData::new_with_u8_clamped_array_and_sh(wasm_bindgen::Clamped(data), width as u32, height as u32)
+}
+
+fn dimensions(data: &[u8], width: u32) -> Result<(usize, usize), JsValue> {
+    let width = width as usize;
+    if width == 0 || !data.len().is_multiple_of(width * 4) {
+        return Err(JsValue::from_str("Pixel data doesn't match the width"));
+    }
+    Ok((width, data.len() / 4 / width))
+}
+
+// Resize an image with a filter (0=nearest, 1=bilinear, 2=bicubic, 3=Lanczos)
+#[wasm_bindgen]
+pub fn resize_image(data: &[u8], width: u32, new_width: u32, new_height: u32, filter: usize) -> Result<ImageData, JsValue> {
+    let (width, height) = dimensions(data, width)?;
+    if new_width == 0 || new_height == 0 {
+        return Err(JsValue::from_str("Size must not be zero"));
+    }
+    let out = resize(data, width, height, new_width as usize, new_height as usize, filter.min(3));
+    image_data(&out, new_width as usize, new_height as usize)
+}
+
+// Cut out a rectangle in pixels, clipped to the image
+#[wasm_bindgen]
+pub fn crop_image(data: &[u8], width: u32, x: u32, y: u32, crop_width: u32, crop_height: u32) -> Result<ImageData, JsValue> {
+    let (width, height) = dimensions(data, width)?;
+    let (x, y) = (x as usize, y as usize);
+    let crop_width = (crop_width as usize).min(width.saturating_sub(x));
+    let crop_height = (crop_height as usize).min(height.saturating_sub(y));
+    if crop_width == 0 || crop_height == 0 {
+        return Err(JsValue::from_str("Crop is outside the image"));
+    }
+    let out: Vec<u8> = (y..y + crop_height)
+        .flat_map(|row| &data[(row * width + x) * 4..(row * width + x + crop_width) * 4])
+        .copied()
+        .collect();
+    image_data(&out, crop_width, crop_height)
+}
+
+// Rotate by any angle clockwise. With `expand` the canvas grows to hold the whole rotated
+// image, otherwise it keeps its size and the corners are cut off; uncovered areas are transparent.
+#[wasm_bindgen]
+pub fn rotate_image(data: &[u8], width: u32, degrees: f64, filter: usize, expand: bool) -> Result<ImageData, JsValue> {
+    let (width, height) = dimensions(data, width)?;
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (out_width, out_height) = if expand {
+        let (w, h) = (width as f64, height as f64);
+        // Round away float noise, so right angles don't gain a pixel
+        let size = |value: f64| ((value * 1e6).round() / 1e6).ceil().max(1.0) as usize;
+        (size(w * cos.abs() + h * sin.abs()), size(w * sin.abs() + h * cos.abs()))
+    } else {
+        (width, height)
+    };
+    let matrix = multiply(
+        &translate(out_width as f64 / 2.0, out_height as f64 / 2.0),
+        &multiply(&rotation(degrees), &translate(-(width as f64) / 2.0, -(height as f64) / 2.0)),
+    );
+    let inverse = invert(&matrix).unwrap_or(IDENTITY);
+    let out = resample(data, width, height, out_width, out_height, filter.min(3), Edge::Transparent, |x, y| Some((
+        inverse[0][0] * x + inverse[0][1] * y + inverse[0][2],
+        inverse[1][0] * x + inverse[1][1] * y + inverse[1][2],
+    )));
+    image_data(&out, out_width, out_height)
+}
+
+// Mirror horizontally and/or vertically, without resampling
+#[wasm_bindgen]
+pub fn flip_image(data: &[u8], width: u32, horizontal: bool, vertical: bool) -> Result<ImageData, JsValue> {
+    let (width, height) = dimensions(data, width)?;
+    let mut out = vec![0; data.len()];
+    for y in 0..height {
+        let src_y = if vertical { height - 1 - y } else { y };
+        for x in 0..width {
+            let src_x = if horizontal { width - 1 - x } else { x };
+            let (dst, src) = ((y * width + x) * 4, (src_y * width + src_x) * 4);
+            out[dst..dst + 4].copy_from_slice(&data[src..src + 4]);
+        }
+    }
+    image_data(&out, width, height)
+}
@@ -0,0 +1,41 @@
+// Web Workers backing the rayon thread pool of a `parallel` build, see src/threads.rs.
+// wasm-bindgen copies this file to pkg/snippets/<crate>/src/, three levels below the package.
+
+function waitForMsgType(target, type) {
+  return new Promise(resolve => {
+    target.addEventListener('message', function onMsg({ data }) {
+      if (data?.type !== type) return;
+      target.removeEventListener('message', onMsg);
+      resolve(data);
+    });
+  });
+}
+
+// In a pool worker: instantiate the module on the shared memory, then hand this thread to rayon
+waitForMsgType(self, 'glitch_worker_init').then(async ({ init, receiver }) => {
+  const pkg = await import('../../../glitch_wasm.js');
+  await pkg.default(init);
+  postMessage({ type: 'glitch_worker_ready' });
+  pkg.wbg_rayon_start_worker(receiver);
+});
+
+// Kept for the lifetime of the page
+let workers = [];
+
+export async function startWorkers(module, memory, builder) {
+  const workerInit = {
+    type: 'glitch_worker_init',
+    init: { module_or_path: module, memory },
+    receiver: builder.receiver()
+  };
+
+  workers = await Promise.all(
+    Array.from({ length: builder.numThreads() }, async () => {
+      const worker = new Worker(new URL('./workerHelpers.js', import.meta.url), { type: 'module' });
+      worker.postMessage(workerInit);
+      await waitForMsgType(worker, 'glitch_worker_ready');
+      return worker;
+    })
+  );
+  builder.build();
+}
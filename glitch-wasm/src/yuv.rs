@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{GlitchOptions, PixelSortOptions};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlaneEffects {
+    pub(crate) plane: usize,           // 0=Y, 1=Cb, 2=Cr
+    pub(crate) effects: GlitchOptions, // Effects run on a grayscale view of the plane
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct YuvOptions {
+    pub(crate) subsampling: Option<usize>,           // 0=4:4:4, 1=4:2:2, 2=4:2:0, 3=4:1:1
+    pub(crate) chroma_shift_x: Option<i32>,          // Horizontal chroma offset in pixels
+    pub(crate) chroma_shift_y: Option<i32>,          // Vertical chroma offset in pixels
+    pub(crate) luma_sort: Option<PixelSortOptions>,  // Pixel sort the Y plane only
+    pub(crate) planes: Option<Vec<PlaneEffects>>,    // Existing effects routed to a single plane
+}
+
+// BT.601 full-range (JPEG) RGB to YCbCr
+pub fn rgb_to_ycbcr(r: u8, g: u8, b: u8) -> [u8; 3] {
+    let (r, g, b) = (r as f64, g as f64, b as f64);
+    let clamp = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+    [
+        clamp(0.299 * r + 0.587 * g + 0.114 * b),
+        clamp(128.0 - 0.168_736 * r - 0.331_264 * g + 0.5 * b),
+        clamp(128.0 + 0.5 * r - 0.418_688 * g - 0.081_312 * b),
+    ]
+}
+
+// BT.601 full-range YCbCr to RGB
+pub fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let y = y as f64;
+    let cb = cb as f64 - 128.0;
+    let cr = cr as f64 - 128.0;
+    let clamp = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+    [
+        clamp(y + 1.402 * cr),
+        clamp(y - 0.344_136 * cb - 0.714_136 * cr),
+        clamp(y + 1.772 * cb),
+    ]
+}
+
+// Average a plane over blocks and repeat the average over the block (nearest upsampling)
+pub fn subsample(plane: &mut [u8], width: usize, block_width: usize, block_height: usize) {
+    let height = plane.len() / width;
+    for by in (0..height).step_by(block_height) {
+        for bx in (0..width).step_by(block_width) {
+            let y_end = (by + block_height).min(height);
+            let x_end = (bx + block_width).min(width);
+            let mut sum = 0u32;
+            for y in by..y_end {
+                for x in bx..x_end {
+                    sum += plane[y * width + x] as u32;
+                }
+            }
+            let count = ((y_end - by) * (x_end - bx)) as u32;
+            let average = ((sum + count / 2) / count) as u8;
+            for y in by..y_end {
+                plane[y * width + bx..y * width + x_end].fill(average);
+            }
+        }
+    }
+}
+
+// Offset a plane by whole pixels with wrap-around
+pub fn shift_plane(plane: &mut [u8], width: usize, dx: i32, dy: i32) {
+    let height = plane.len() / width;
+    let source = plane.to_vec();
+    for y in 0..height {
+        let sy = (y as i64 - dy as i64).rem_euclid(height as i64) as usize;
+        for x in 0..width {
+            let sx = (x as i64 - dx as i64).rem_euclid(width as i64) as usize;
+            plane[y * width + x] = source[sy * width + sx];
+        }
+    }
+}
@@ -1,5 +1,5 @@
 import { useState, useEffect, useRef } from 'react';
-import { GlitchOptions } from './useGlitchEffect';
+import { GlitchOptions, ModulationRoute } from './useGlitchEffect';
 import { AudioData } from './useAudioAnalyzer';
 
 interface AnimationOptions {
@@ -11,7 +11,7 @@ interface AnimationOptions {
 }
 
 export const useAnimatedEffects = (
-  initialEffects: GlitchOptions, 
+  initialEffects: GlitchOptions,
   audioData: AudioData
 ): [GlitchOptions, (newOptions: Partial<AnimationOptions>) => void, AnimationOptions] => {
   const [animationOptions, setAnimationOptions] = useState<AnimationOptions>({
@@ -24,9 +24,12 @@ export const useAnimatedEffects = (
 
   const [currentEffects, setCurrentEffects] = useState<GlitchOptions>(initialEffects);
   const baseEffectsRef = useRef<GlitchOptions>(initialEffects);
-  const timeRef = useRef<number>(0);
+  const audioDataRef = useRef<AudioData>(audioData);
   const animationFrameRef = useRef<number | null>(null);
 
+  // The animation loop reads the latest audio levels on every frame
+  audioDataRef.current = audioData;
+
   // Update base effects when initialEffects change
   useEffect(() => {
     baseEffectsRef.current = initialEffects;
@@ -46,7 +49,7 @@ export const useAnimatedEffects = (
     return () => {
       stopAnimation();
     };
-  }, [animationOptions.enabled, animationOptions.speed, animationOptions.intensity, animationOptions.audioReactive]);
+  }, [animationOptions.enabled, animationOptions.speed, animationOptions.intensity, animationOptions.audioReactive, animationOptions.chaotic]);
 
   const startAnimation = () => {
     if (animationFrameRef.current) {
@@ -54,22 +57,21 @@ export const useAnimatedEffects = (
     }
 
     const animate = (timestamp: number) => {
-      const deltaTime = timestamp - timeRef.current;
-      timeRef.current = timestamp;
-
-      // Animate the effects - always starting with a fresh copy of base effects
-      const newEffects = animateEffects(
-        JSON.parse(JSON.stringify(baseEffectsRef.current)), // Create a deep copy to avoid mutation
-        deltaTime,
-        animationOptions,
-        audioData
-      );
-      setCurrentEffects(newEffects);
+      // The engine evaluates the routes at this time, so a frame only depends on the time
+      // and the seed, and exports render the same frames as the preview
+      const baseEffects = baseEffectsRef.current;
+      setCurrentEffects({
+        ...baseEffects,
+        modulation: [
+          ...(baseEffects.modulation ?? []),
+          ...animationRoutes(baseEffects, animationOptions, audioDataRef.current)
+        ],
+        time: timestamp / 1000
+      });
 
       animationFrameRef.current = requestAnimationFrame(animate);
     };
 
-    timeRef.current = performance.now();
     animationFrameRef.current = requestAnimationFrame(animate);
   };
 
@@ -87,214 +89,81 @@ export const useAnimatedEffects = (
   return [currentEffects, updateAnimationOptions, animationOptions];
 };
 
-// Helper function to animate effects
-function animateEffects(
-  baseEffects: GlitchOptions, 
-  deltaTime: number, 
+// Modulation routes for the animated parameters of the base effects
+function animationRoutes(
+  effects: GlitchOptions,
   options: AnimationOptions,
   audioData: AudioData
-): GlitchOptions {
-  // We're now working with a fresh copy of baseEffects, so no need to clone again
-  const newEffects = baseEffects;
-  
-  // Calculate time factors (for oscillations)
-  const timeScale = options.speed * 0.001;
-  const time = performance.now() * timeScale;
-  
-  // Base intensity multiplier (0.5-2.0)
+): ModulationRoute[] {
+  const routes: ModulationRoute[] = [];
+  const audioReactive = options.audioReactive && audioData.isActive;
+
+  // Base intensity multiplier (0.5-2.0), scaled up by the volume when reacting to audio
   let intensityMultiplier = 0.5 + options.intensity * 1.5;
+  if (audioReactive) {
+    intensityMultiplier *= 1 + audioData.volume * 2;
+  }
 
-  // Function to generate value based on animation mode
-  const generateValue = (baseValue: number, time: number, amplitude: number): number => {
-    if (options.chaotic) {
-      // In chaotic mode, we use random jumps that change based on time
-      // We use a seeded random approach where the seed changes with time
-      const seed = Math.sin(time * 10) * 10000;
-      const randomValue = Math.sin(seed) * 0.5 + 0.5; // Convert to 0-1 range
-      return Math.max(0, Math.min(1, baseValue + (randomValue * 2 - 1) * amplitude));
-    } else {
-      // In smooth mode, we use sinusoidal oscillation (the original approach)
-      const oscillation = Math.sin(time) * amplitude;
-      return Math.max(0, Math.min(1, baseValue + oscillation));
-    }
+  // Frequency-specific boosts (none without audio)
+  const bassFactor = audioReactive ? audioData.bass * 2 : 0;
+  const midFactor = audioReactive ? audioData.mid * 1.5 : 0;
+  const trebleFactor = audioReactive ? audioData.treble : 0;
+
+  // Smooth mode is a sine LFO, chaotic mode jumps to a new random value ten times as often.
+  // `frequency` is relative to the animation speed; each route gets its own random stream.
+  const route = (target: string, frequency: number, depth: number) => {
+    const rate = options.speed * frequency / (2 * Math.PI);
+    routes.push({
+      target,
+      modulator: options.chaotic
+        ? { shape: 4, rate: rate * 10, seed: routes.length }
+        : { shape: 0, rate },
+      depth
+    });
   };
 
-  // Apply audio reactivity if enabled
-  if (options.audioReactive && audioData.isActive) {
-    // Scale intensity based on audio volume
-    intensityMultiplier *= 1 + audioData.volume * 2;
-    
-    // Apply frequency-specific modulations
-    const bassFactor = audioData.bass * 2;
-    const midFactor = audioData.mid * 1.5;
-    const trebleFactor = audioData.treble;
-    
-    // Animate pixel sort based on bass
-    if (newEffects.pixelSort) {
-      newEffects.pixelSort = {
-        ...newEffects.pixelSort,
-        intensity: generateValue(
-          newEffects.pixelSort.intensity || 0.5,
-          time,
-          0.1 * intensityMultiplier * (1 + bassFactor)
-        ),
-        // Animate threshold too
-        threshold: generateValue(
-          newEffects.pixelSort.threshold || 0.5,
-          time * 0.62,
-          0.08 * intensityMultiplier * (1 + midFactor)
-        )
-      };
-      
-      if (intensityMultiplier > 1.2 && Math.sin(time * 0.2) > 0.95) {
-        newEffects.pixelSort.vertical = !newEffects.pixelSort.vertical;
-      }
-    }
-    
-    // Animate data bend based on mids
-    if (newEffects.dataBend) {
-      newEffects.dataBend = {
-        ...newEffects.dataBend,
-        amount: generateValue(
-          newEffects.dataBend.amount || 0.5,
-          time * 1.3, // Different frequency
-          0.15 * intensityMultiplier * (1 + midFactor)
-        )
-      };
-    }
-    
-    // Animate channel shift based on treble
-    if (newEffects.channelShift) {
-      newEffects.channelShift = {
-        ...newEffects.channelShift,
-        amount: generateValue(
-          newEffects.channelShift.amount || 0.5,
-          time * 0.7, // Different frequency
-          0.12 * intensityMultiplier * (1 + trebleFactor)
-        )
-      };
-    }
-    
-    // Animate noise based on overall volume spikes
-    if (typeof newEffects.noise === 'number') {
-      newEffects.noise = Math.min(
-        1, 
-        (newEffects.noise || 0) + (audioData.volume * options.intensity * 0.5)
-      );
-    }
-    
-    // Animate channel-specific effects
-    if (newEffects.redChannel) {
-      if (newEffects.redChannel.pixelSort) {
-        newEffects.redChannel.pixelSort.intensity = generateValue(
-          newEffects.redChannel.pixelSort.intensity || 0,
-          time * 0.8,
-          0.2 * intensityMultiplier * bassFactor
-        );
-      }
-      
-      if (newEffects.redChannel.noise !== undefined) {
-        newEffects.redChannel.noise = generateValue(
-          newEffects.redChannel.noise || 0,
-          time * 1.2,
-          0.2 * intensityMultiplier * trebleFactor
-        );
-      }
-    }
-    
-    if (newEffects.greenChannel) {
-      if (newEffects.greenChannel.dataBend) {
-        newEffects.greenChannel.dataBend.amount = generateValue(
-          newEffects.greenChannel.dataBend.amount || 0,
-          time * 0.9,
-          0.2 * intensityMultiplier * midFactor
-        );
-      }
-    }
-    
-    if (newEffects.blueChannel) {
-      if (newEffects.blueChannel.dataBend) {
-        newEffects.blueChannel.dataBend.amount = generateValue(
-          newEffects.blueChannel.dataBend.amount || 0,
-          time * 1.1,
-          0.2 * intensityMultiplier * (bassFactor + trebleFactor) / 2
-        );
-      }
-    }
-  } else {
-    // Regular animation without audio reactivity
-    // Animate pixel sort
-    if (newEffects.pixelSort) {
-      newEffects.pixelSort = {
-        ...newEffects.pixelSort,
-        intensity: generateValue(
-          newEffects.pixelSort.intensity || 0.5,
-          time,
-          0.1 * intensityMultiplier
-        ),
-        // Animate threshold too
-        threshold: generateValue(
-          newEffects.pixelSort.threshold || 0.5,
-          time * 0.62, // Different frequency for interesting combinations
-          0.08 * intensityMultiplier
-        )
-      };
-      
-      // Less frequent vertical toggle for non-audio mode
-      if (intensityMultiplier > 1.3 && Math.sin(time * 0.15) > 0.97) {
-        newEffects.pixelSort.vertical = !newEffects.pixelSort.vertical;
-      }
-    }
-    
-    // Animate data bend
-    if (newEffects.dataBend) {
-      newEffects.dataBend = {
-        ...newEffects.dataBend,
-        amount: generateValue(
-          newEffects.dataBend.amount || 0.5,
-          time * 1.3, // Different frequency
-          0.15 * intensityMultiplier
-        )
-      };
-    }
-    
-    // Animate channel shift
-    if (newEffects.channelShift) {
-      newEffects.channelShift = {
-        ...newEffects.channelShift,
-        amount: generateValue(
-          newEffects.channelShift.amount || 0.5,
-          time * 0.7, // Different frequency
-          0.12 * intensityMultiplier
-        )
-      };
-    }
-    
-    // Channel-specific subtle animations
-    if (newEffects.redChannel?.pixelSort) {
-      newEffects.redChannel.pixelSort.intensity = generateValue(
-        newEffects.redChannel.pixelSort.intensity || 0,
-        time * 0.8,
-        0.05 * intensityMultiplier
-      );
-    }
-    
-    if (newEffects.greenChannel?.dataBend) {
-      newEffects.greenChannel.dataBend.amount = generateValue(
-        newEffects.greenChannel.dataBend.amount || 0,
-        time * 0.9,
-        0.07 * intensityMultiplier
-      );
-    }
-    
-    if (newEffects.blueChannel?.dataBend) {
-      newEffects.blueChannel.dataBend.amount = generateValue(
-        newEffects.blueChannel.dataBend.amount || 0,
-        time * 1.1,
-        0.06 * intensityMultiplier
-      );
-    }
+  if (effects.pixelSort) {
+    route('pixel_sort.intensity', 1, 0.1 * intensityMultiplier * (1 + bassFactor));
+    route('pixel_sort.threshold', 0.62, 0.08 * intensityMultiplier * (1 + midFactor));
+  }
+
+  if (effects.dataBend) {
+    route('data_bend.amount', 1.3, 0.15 * intensityMultiplier * (1 + midFactor));
+  }
+
+  if (effects.channelShift) {
+    route('channel_shift.amount', 0.7, 0.12 * intensityMultiplier * (1 + trebleFactor));
+  }
+
+  // Noise follows the overall volume
+  if (audioReactive && typeof effects.noise === 'number') {
+    routes.push({
+      target: 'noise',
+      modulator: { shape: 0, rate: 0 },
+      depth: 0,
+      offset: audioData.volume * options.intensity * 0.5
+    });
+  }
+
+  // Channel-specific animations, stronger when reacting to audio
+  if (effects.redChannel?.pixelSort) {
+    route('red_channel.pixel_sort.intensity', 0.8,
+      audioReactive ? 0.2 * intensityMultiplier * bassFactor : 0.05 * intensityMultiplier);
+  }
+
+  if (audioReactive && effects.redChannel?.noise !== undefined) {
+    route('red_channel.noise', 1.2, 0.2 * intensityMultiplier * trebleFactor);
+  }
+
+  if (effects.greenChannel?.dataBend) {
+    route('green_channel.data_bend.amount', 0.9,
+      audioReactive ? 0.2 * intensityMultiplier * midFactor : 0.07 * intensityMultiplier);
   }
-  
-  return newEffects;
-} 
\ No newline at end of file
+
+  if (effects.blueChannel?.dataBend) {
+    route('blue_channel.data_bend.amount', 1.1,
+      audioReactive ? 0.2 * intensityMultiplier * (bassFactor + trebleFactor) / 2 : 0.06 * intensityMultiplier);
+  }
+
+  return routes;
+}
@@ -1,5 +1,6 @@
-import { useEffect, useState } from 'react';
-import init, { GlitchEffect } from 'glitch-wasm';
+import { useEffect, useRef, useState } from 'react';
+import init, * as glitchWasm from 'glitch-wasm';
+import { GlitchEffect } from 'glitch-wasm';
 import { BlendImage, BlendOptions, getBlendModeValue } from './useImageBlender';
 
 export interface PixelSortOptions {
@@ -58,6 +59,29 @@ export interface ChannelEffects {
   binaryXor?: BinaryXorOptions; // Binary XOR operation
 }
 
+// A modulator evaluated by the engine at the options' `time` (see modulation.rs)
+export interface ModulatorOptions {
+  shape: number;     // 0=sine, 1=triangle, 2=square, 3=saw, 4=sample & hold, 5=Perlin noise, 6=envelope, 7=simplex noise
+  rate: number;      // Cycles per second
+  phase?: number;    // Phase offset in cycles (0.0-1.0)
+  seed?: number;     // Seed for the random shapes
+  attack?: number;   // Envelope times in seconds and sustain level
+  decay?: number;
+  sustain?: number;
+  release?: number;
+  trigger?: number;
+  gate?: number;
+}
+
+export interface ModulationRoute {
+  target: string;    // Engine parameter path, e.g. "data_bend.amount" or "red_channel.noise"
+  modulator: ModulatorOptions;
+  depth: number;     // How far the modulator moves the parameter
+  offset?: number;   // Constant added to the base value
+  min?: number;
+  max?: number;
+}
+
 export interface GlitchOptions {
   // Global effects that work across all channels
   pixelSort?: PixelSortOptions;
@@ -77,8 +101,26 @@ export interface GlitchOptions {
   redChannel?: ChannelEffects;
   greenChannel?: ChannelEffects;
   blueChannel?: ChannelEffects;
+
+  // Animation: the engine applies the routes at `time` (seconds)
+  seed?: number;
+  time?: number;
+  modulation?: ModulationRoute[];
 }
 
+// Only builds with the `parallel` feature export initThreadPool, and its workers need
+// SharedArrayBuffer, so the pool is started when both are available. The pool is global
+// and can only be started once per page.
+let threadPool: Promise<void> | null = null;
+
+const startThreadPool = () => {
+  const { initThreadPool } = glitchWasm as unknown as { initThreadPool?: (threads: number) => Promise<void> };
+  if (!threadPool && initThreadPool && self.crossOriginIsolated) {
+    threadPool = initThreadPool(navigator.hardwareConcurrency || 4);
+  }
+  return threadPool;
+};
+
 export const useGlitchEffect = () => {
   const [glitchEffect, setGlitchEffect] = useState<GlitchEffect | null>(null);
   const [isLoading, setIsLoading] = useState(true);
@@ -88,6 +130,7 @@ export const useGlitchEffect = () => {
     const initWasm = async () => {
       try {
         await init();
+        await startThreadPool();
         const effect = new GlitchEffect();
         setGlitchEffect(effect);
         setIsLoading(false);
@@ -142,319 +185,110 @@ export const useGlitchEffect = () => {
     }
   };
 
-  // Move the original applyEffects implementation to a separate function
+  // Render the options with the engine's full pipeline in one call
   const applyEffectsToImageData = (imageData: ImageData, options: GlitchOptions): ImageData | null => {
     if (!glitchEffect) return null;
 
     try {
-      // Create a copy of the image data to work with
-      const canvas = document.createElement('canvas');
-      canvas.width = imageData.width;
-      canvas.height = imageData.height;
-      const ctx = canvas.getContext('2d');
-      if (!ctx) return null;
-      
-      ctx.putImageData(imageData, 0, 0);
-      const newImageData = ctx.getImageData(0, 0, canvas.width, canvas.height);
-      
-      // Create a copy of the pixel data we can modify
-      const pixelData = new Uint8Array(newImageData.data);
-      
-      // Apply global effects that work on all channels together
-      applyGlobalEffects(pixelData, newImageData.width, options);
-      
-      // If we have per-channel effects, extract channels, apply effects separately, and recombine
-      if (options.redChannel || options.greenChannel || options.blueChannel) {
-        applyChannelEffects(pixelData, newImageData.width, options);
-      }
-      
-      // Create a new ImageData with the modified pixels
-      return new ImageData(
-        new Uint8ClampedArray(pixelData.buffer),
-        newImageData.width,
-        newImageData.height
-      );
+      return glitchEffect.apply_effects(imageData, toEngineOptions(options));
     } catch (err) {
       console.error('Error applying effects:', err);
       return null;
     }
   };
 
-  // Helper function to apply global effects to all channels
-  const applyGlobalEffects = (pixelData: Uint8Array, width: number, options: GlitchOptions) => {
-    if (!glitchEffect) return;
-    
-    // 1. Pixel Sort
-    if (options.pixelSort && options.pixelSort.intensity > 0) {
-      glitchEffect.pixel_sort(
-        pixelData,
-        width,
-        options.pixelSort.intensity,
-        options.pixelSort.threshold,
-        options.pixelSort.vertical,
-        options.pixelSort.channel === undefined ? null : options.pixelSort.channel
-      );
-    }
-    
-    // 2. Data Bend
-    if (options.dataBend && options.dataBend.amount > 0) {
-      glitchEffect.data_bend(
-        pixelData,
-        options.dataBend.amount,
-        options.dataBend.mode === undefined ? null : options.dataBend.mode,
-        options.dataBend.chunkSize === undefined ? null : options.dataBend.chunkSize,
-        options.dataBend.channel === undefined ? null : options.dataBend.channel
-      );
-    }
-    
-    // 3. Channel Shift
-    if (options.channelShift && options.channelShift.amount > 0) {
-      // Create a clean new array for channels to avoid aliasing
-      let channelsArray = null;
-      if (options.channelShift.channels && options.channelShift.channels.length > 0) {
-        // Create a primitive copy first to avoid reference issues
-        const primitiveArray = [...options.channelShift.channels].map(Number);
-        channelsArray = new Uint32Array(primitiveArray);
-      }
-      
-      glitchEffect.channel_shift(
-        pixelData,
-        options.channelShift.amount,
-        channelsArray,
-        options.channelShift.direction === undefined ? null : options.channelShift.direction
-      );
-    }
-    
-    // 4. Noise
-    if (options.noise && options.noise > 0) {
-      glitchEffect.add_noise(pixelData, options.noise);
-    }
-    
-    // 5. Invert
-    if (options.invert && options.invert.length > 0) {
-      // Create a clean new array to avoid aliasing
-      const primitiveArray = [...options.invert].map(Number);
-      const invertArray = new Uint32Array(primitiveArray);
-      glitchEffect.invert_channels(pixelData, invertArray);
-    }
-    
-    // 6. Quantize
-    if (options.quantize && options.quantize < 256) {
-      glitchEffect.quantize(pixelData, options.quantize);
-    }
-    
-    // 7. Byte Corruption
-    if (options.byteCorrupt && options.byteCorrupt.amount > 0) {
-      glitchEffect.byte_corrupt(
-        pixelData,
-        options.byteCorrupt.amount,
-        options.byteCorrupt.mode === undefined ? null : options.byteCorrupt.mode,
-        options.byteCorrupt.blockSize === undefined ? null : options.byteCorrupt.blockSize,
-        options.byteCorrupt.structured
-      );
-    }
-    
-    // 8. Chunk Swap
-    if (options.chunkSwap && options.chunkSwap.amount > 0) {
-      glitchEffect.chunk_swap(
-        pixelData,
-        width,
-        options.chunkSwap.amount,
-        options.chunkSwap.chunkSize === undefined ? null : options.chunkSwap.chunkSize,
-        options.chunkSwap.preserveAlpha
-      );
-    }
-    
-    // 9. Binary XOR
-    if (options.binaryXor && options.binaryXor.strength > 0) {
-      let patternArray = null;
-      if (options.binaryXor.pattern && options.binaryXor.pattern.length > 0) {
-        // Create a primitive Uint8Array from the pattern
-        patternArray = new Uint8Array(options.binaryXor.pattern);
-      }
-      
-      glitchEffect.binary_xor(
-        pixelData,
-        width,
-        patternArray,
-        options.binaryXor.strength,
-        options.binaryXor.mode === undefined ? null : options.binaryXor.mode
-      );
-    }
-    
-    // 10. Image Blend
-    if (options.imageBlend && options.imageBlend.secondaryImage?.data) {
-      const { secondaryImage, blendOptions } = options.imageBlend;
-      
-      glitchEffect.image_blend(
-        pixelData,
-        width,
-        secondaryImage.data,
-        secondaryImage.width,
-        secondaryImage.height,
-        getBlendModeValue(blendOptions.mode),
-        blendOptions.amount,
-        blendOptions.offsetX,
-        blendOptions.offsetY
-      );
-    }
-  };
+  // The blend image is registered with the engine once and updated in place when it changes
+  const blendImageRef = useRef<{ data: Uint8ClampedArray; handle: number } | null>(null);
 
-  // Helper function to extract channels, apply effects, and recombine
-  const applyChannelEffects = (pixelData: Uint8Array, width: number, options: GlitchOptions) => {
-    if (!glitchEffect) return;
-    
-    const pixelCount = pixelData.length / 4;
-    
-    // Extract channels into separate arrays
-    const redChannel = new Uint8Array(pixelCount);
-    const greenChannel = new Uint8Array(pixelCount);
-    const blueChannel = new Uint8Array(pixelCount);
-    const alphaChannel = new Uint8Array(pixelCount);
-    
-    // Extract channels
-    for (let i = 0; i < pixelCount; i++) {
-      const offset = i * 4;
-      redChannel[i] = pixelData[offset];
-      greenChannel[i] = pixelData[offset + 1];
-      blueChannel[i] = pixelData[offset + 2];
-      alphaChannel[i] = pixelData[offset + 3];
-    }
-    
-    // Apply effects to individual channels
-    
-    // Red channel effects
-    if (options.redChannel) {
-      applyEffectsToChannel(redChannel, width, options.redChannel);
-    }
-    
-    // Green channel effects
-    if (options.greenChannel) {
-      applyEffectsToChannel(greenChannel, width, options.greenChannel);
-    }
-    
-    // Blue channel effects
-    if (options.blueChannel) {
-      applyEffectsToChannel(blueChannel, width, options.blueChannel);
-    }
-    
-    // Recombine channels
-    for (let i = 0; i < pixelCount; i++) {
-      const offset = i * 4;
-      pixelData[offset] = redChannel[i];
-      pixelData[offset + 1] = greenChannel[i];
-      pixelData[offset + 2] = blueChannel[i];
-      pixelData[offset + 3] = alphaChannel[i]; // Keep original alpha
-    }
+  const blendImageHandle = (image: BlendImage): number | undefined => {
+    if (!glitchEffect || !image.data) return undefined;
+    const current = blendImageRef.current;
+    if (current && current.data === image.data) return current.handle;
+
+    const bytes = new Uint8Array(image.data.buffer, image.data.byteOffset, image.data.byteLength);
+    if (current) {
+      glitchEffect.update_image(current.handle, bytes, image.width, image.height);
+      blendImageRef.current = { data: image.data, handle: current.handle };
+    } else {
+      blendImageRef.current = { data: image.data, handle: glitchEffect.register_image(bytes, image.width, image.height) };
+    }
+    return blendImageRef.current.handle;
   };
 
-  // Helper function to apply effects to a single channel
-  const applyEffectsToChannel = (channelData: Uint8Array, width: number, effects: ChannelEffects) => {
-    if (!glitchEffect) return;
-    
-    // We need to transform single-channel data to RGBA for the Wasm functions to work
-    // Create a temporary RGBA array where our channel is duplicated to all RGB components
-    const rgbaData = new Uint8Array(channelData.length * 4);
-    
-    // Copy the channel data to RGB components, set alpha to 255
-    for (let i = 0; i < channelData.length; i++) {
-      const value = channelData[i];
-      const offset = i * 4;
-      rgbaData[offset] = value;     // R
-      rgbaData[offset + 1] = value; // G
-      rgbaData[offset + 2] = value; // B
-      rgbaData[offset + 3] = 255;   // Alpha
-    }
-    
-    // Now apply the effects to this expanded array
-    
-    // 1. Pixel Sort
-    if (effects.pixelSort && effects.pixelSort.intensity > 0) {
-      glitchEffect.pixel_sort(
-        rgbaData,
-        width,
-        effects.pixelSort.intensity,
-        effects.pixelSort.threshold,
-        effects.pixelSort.vertical,
-        effects.pixelSort.channel === undefined ? null : effects.pixelSort.channel
-      );
-    }
-    
-    // 2. Data Bend
-    if (effects.dataBend && effects.dataBend.amount > 0) {
-      glitchEffect.data_bend(
-        rgbaData,
-        effects.dataBend.amount,
-        effects.dataBend.mode === undefined ? null : effects.dataBend.mode,
-        effects.dataBend.chunkSize === undefined ? null : effects.dataBend.chunkSize,
-        effects.dataBend.channel === undefined ? null : effects.dataBend.channel
-      );
-    }
-    
-    // 3. Shift (basically a mini channel shift but on one channel)
-    if (effects.shift && effects.shift.amount > 0) {
-      glitchEffect.channel_shift(
-        rgbaData,
-        effects.shift.amount,
-        new Uint32Array([0, 1, 2]), // all RGB channels since they contain copies of our data
-        effects.shift.direction === undefined ? null : effects.shift.direction
-      );
-    }
-    
-    // 4. Noise
-    if (effects.noise && effects.noise > 0) {
-      glitchEffect.add_noise(rgbaData, effects.noise);
-    }
-    
-    // 5. Invert
-    if (effects.invert) {
-      glitchEffect.invert_channels(rgbaData, new Uint32Array([0, 1, 2])); // All RGB components
-    }
-    
-    // 6. Quantize
-    if (effects.quantize && effects.quantize < 256) {
-      glitchEffect.quantize(rgbaData, effects.quantize);
-    }
-    
-    // 7. Byte Corruption
-    if (effects.byteCorrupt && effects.byteCorrupt.amount > 0) {
-      glitchEffect.byte_corrupt(
-        rgbaData,
-        effects.byteCorrupt.amount,
-        effects.byteCorrupt.mode === undefined ? null : effects.byteCorrupt.mode,
-        effects.byteCorrupt.blockSize === undefined ? null : effects.byteCorrupt.blockSize,
-        effects.byteCorrupt.structured
-      );
-    }
-    
-    // 8. Binary XOR
-    if (effects.binaryXor && effects.binaryXor.strength > 0) {
-      let patternArray = null;
-      if (effects.binaryXor.pattern && effects.binaryXor.pattern.length > 0) {
-        patternArray = new Uint8Array(effects.binaryXor.pattern);
-      }
-      
-      glitchEffect.binary_xor(
-        rgbaData,
-        width,
-        patternArray,
-        effects.binaryXor.strength,
-        effects.binaryXor.mode === undefined ? null : effects.binaryXor.mode
-      );
-    }
-    
-    // Now extract the channel data back from RGB (we'll average R, G, B for best effect)
-    for (let i = 0; i < channelData.length; i++) {
-      const offset = i * 4;
-      const r = rgbaData[offset];
-      const g = rgbaData[offset + 1];
-      const b = rgbaData[offset + 2];
-      
-      // Average the RGB values for the final channel value
-      channelData[i] = Math.round((r + g + b) / 3);
-    }
+  // Convert the UI options to the engine's snake_case options, leaving out effects at zero
+  // strength like the individual calls used to
+  const toEngineOptions = (options: GlitchOptions) => {
+    const blend = options.imageBlend;
+    const blendHandle = blend ? blendImageHandle(blend.secondaryImage) : undefined;
+
+    return {
+      ...toEngineCommonEffects(options),
+      channel_shift: options.channelShift && options.channelShift.amount > 0 ? {
+        amount: options.channelShift.amount,
+        channels: options.channelShift.channels?.length ? options.channelShift.channels.map(Number) : undefined,
+        direction: options.channelShift.direction,
+      } : undefined,
+      invert: options.invert?.length ? options.invert.map(Number) : undefined,
+      chunk_swap: options.chunkSwap && options.chunkSwap.amount > 0 ? {
+        amount: options.chunkSwap.amount,
+        chunk_size: options.chunkSwap.chunkSize,
+        preserve_alpha: options.chunkSwap.preserveAlpha,
+      } : undefined,
+      image_blend: blend && blendHandle !== undefined ? {
+        secondary_image: blendHandle,
+        blend_mode: getBlendModeValue(blend.blendOptions.mode),
+        amount: blend.blendOptions.amount,
+        offset_x: blend.blendOptions.offsetX,
+        offset_y: blend.blendOptions.offsetY,
+      } : undefined,
+      red_channel: options.redChannel && toEngineChannelEffects(options.redChannel),
+      green_channel: options.greenChannel && toEngineChannelEffects(options.greenChannel),
+      blue_channel: options.blueChannel && toEngineChannelEffects(options.blueChannel),
+      seed: options.seed,
+      time: options.time,
+      modulation: options.modulation,
+    };
   };
 
+  // Per-channel chains run on a grayscale view of the channel in the engine
+  const toEngineChannelEffects = (effects: ChannelEffects) => ({
+    ...toEngineCommonEffects(effects),
+    shift: effects.shift && effects.shift.amount > 0 ? {
+      amount: effects.shift.amount,
+      direction: effects.shift.direction,
+    } : undefined,
+    invert: effects.invert || undefined,
+  });
+
+  // Effects with the same options for the whole image and for a single channel
+  const toEngineCommonEffects = (effects: ChannelEffects | GlitchOptions) => ({
+    pixel_sort: effects.pixelSort && effects.pixelSort.intensity > 0 ? {
+      intensity: effects.pixelSort.intensity,
+      threshold: effects.pixelSort.threshold,
+      vertical: effects.pixelSort.vertical,
+      channel: effects.pixelSort.channel,
+    } : undefined,
+    data_bend: effects.dataBend && effects.dataBend.amount > 0 ? {
+      amount: effects.dataBend.amount,
+      mode: effects.dataBend.mode,
+      chunk_size: effects.dataBend.chunkSize,
+      channel: effects.dataBend.channel,
+    } : undefined,
+    noise: effects.noise && effects.noise > 0 ? effects.noise : undefined,
+    quantize: effects.quantize && effects.quantize < 256 ? effects.quantize : undefined,
+    byte_corrupt: effects.byteCorrupt && effects.byteCorrupt.amount > 0 ? {
+      amount: effects.byteCorrupt.amount,
+      mode: effects.byteCorrupt.mode,
+      block_size: effects.byteCorrupt.blockSize,
+      structured: effects.byteCorrupt.structured,
+    } : undefined,
+    binary_xor: effects.binaryXor && effects.binaryXor.strength > 0 ? {
+      pattern: effects.binaryXor.pattern?.length ? effects.binaryXor.pattern : undefined,
+      strength: effects.binaryXor.strength,
+      mode: effects.binaryXor.mode,
+    } : undefined,
+  });
+
   // Individual effect functions for more control
   const pixelSort = (imageData: ImageData, options: PixelSortOptions): ImageData | null => {
     if (!glitchEffect) return null;
@@ -498,7 +332,8 @@ export const useGlitchEffect = () => {
         options.amount, 
         options.mode, 
         options.chunkSize, 
-        options.channel
+        options.channel,
+        imageData.width
       );
       
       // Create new ImageData with the modified data
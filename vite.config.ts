@@ -2,9 +2,28 @@ import { defineConfig } from 'vite'
 import react from '@vitejs/plugin-react'
 import wasm from 'vite-plugin-wasm'
 
+// SharedArrayBuffer, and therefore the thread pool of a parallel glitch-wasm build, is only
+// available on cross-origin isolated pages
+const crossOriginIsolation = {
+  'Cross-Origin-Opener-Policy': 'same-origin',
+  'Cross-Origin-Embedder-Policy': 'require-corp'
+}
+
 export default defineConfig({
   plugins: [react(), wasm()],
   server: {
-    host: true
+    host: true,
+    headers: crossOriginIsolation
+  },
+  preview: {
+    headers: crossOriginIsolation
+  },
+  // Keep glitch-wasm out of dependency pre-bundling so the pool workers of a parallel
+  // build can load their script and the main module next to it
+  optimizeDeps: {
+    exclude: ['glitch-wasm']
+  },
+  worker: {
+    format: 'es'
   }
 }) 
\ No newline at end of file